@@ -49,3 +49,13 @@ tail -c +3 "${ALL[@]}" >$OUT_DIR/all.c+3.out
 tail -c +8 "${ALL[@]}" >$OUT_DIR/all.c+8.out
 tail -c +12 "${ALL[@]}" >$OUT_DIR/all.c+12.out
 tail -n +3 -q "${ALL[@]}" >$OUT_DIR/all.n+3.q.out
+
+tail - <"$ROOT/ten.txt" >$OUT_DIR/stdin.ten.txt.out
+tail -n 1 "$ROOT/one.txt" - <"$ROOT/ten.txt" >$OUT_DIR/stdin.all.n1.out
+
+tail -z -n 2 "$ROOT/zero.bin" >$OUT_DIR/zero.bin.z.n2.out
+tail -z -n +3 "$ROOT/zero.bin" >$OUT_DIR/zero.bin.z.n+3.out
+tail -z -n 2 - <"$ROOT/zero.bin" >$OUT_DIR/stdin.zero.bin.z.n2.out
+
+tail -v "$ROOT/one.txt" >$OUT_DIR/one.txt.v.out
+tail -v -n 1 - <"$ROOT/ten.txt" >$OUT_DIR/stdin.ten.txt.v.n1.out
@@ -0,0 +1,153 @@
+use std::{fmt, io, num::ParseIntError};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A source could not be opened.
+    Open { name: String, source: io::Error },
+    /// Reading a source failed part way through.
+    Read { name: String, source: io::Error },
+    /// A source is a directory.
+    IsDirectory { name: String },
+    /// Seeking within a source failed.
+    Seek { name: String, source: io::Error },
+    /// A line or byte count could not be parsed.
+    InvalidCount(ParseIntError),
+    /// A count with a multiplier suffix does not fit in an `i64`.
+    CountTooLarge,
+    /// A header template could not be parsed.
+    InvalidHeaderFormat(String),
+    /// A timestamp template has an unknown specifier.
+    InvalidTimestampFormat(String),
+    /// Writing the output failed.
+    Output(io::Error),
+    /// Every followed source became inaccessible.
+    NoFilesRemaining,
+}
+
+impl Error {
+    /// Returns the name of the source this error belongs to, if any.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Open { name, .. }
+            | Self::Read { name, .. }
+            | Self::IsDirectory { name }
+            | Self::Seek { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    // Describes the error without the name of its source, for output that
+    // records the name separately.
+    pub(crate) fn reason(&self) -> String {
+        match self {
+            Self::Open { source, .. } | Self::Read { source, .. } | Self::Seek { source, .. } => {
+                source.to_string()
+            }
+            Self::IsDirectory { .. } => "Is a directory".to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    pub(crate) fn open(name: &str, source: io::Error) -> Self {
+        Self::Open {
+            name: name.to_string(),
+            source,
+        }
+    }
+
+    pub(crate) fn read(name: &str, source: io::Error) -> Self {
+        Self::Read {
+            name: name.to_string(),
+            source,
+        }
+    }
+
+    pub(crate) fn seek(name: &str, source: io::Error) -> Self {
+        Self::Seek {
+            name: name.to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open { name, source } => write!(f, "{name}: {source}"),
+            Self::Read { name, source } => write!(f, "error reading '{name}': {source}"),
+            Self::IsDirectory { name } => write!(f, "error reading '{name}': Is a directory"),
+            Self::Seek { name, source } => write!(f, "{name}: {source}"),
+            Self::InvalidCount(source) => write!(f, "{source}"),
+            Self::CountTooLarge => write!(f, "value too large for defined data type"),
+            Self::InvalidHeaderFormat(reason) => write!(f, "invalid header format: {reason}"),
+            Self::InvalidTimestampFormat(format) => {
+                write!(f, "invalid timestamp format '{format}'")
+            }
+            Self::Output(source) => write!(f, "{source}"),
+            Self::NoFilesRemaining => write!(f, "no files remaining"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Open { source, .. }
+            | Self::Read { source, .. }
+            | Self::Seek { source, .. }
+            | Self::Output(source) => Some(source),
+            Self::InvalidCount(source) => Some(source),
+            Self::IsDirectory { .. }
+            | Self::CountTooLarge
+            | Self::InvalidHeaderFormat(_)
+            | Self::InvalidTimestampFormat(_)
+            | Self::NoFilesRemaining => None,
+        }
+    }
+}
+
+impl From<ParseIntError> for Error {
+    fn from(err: ParseIntError) -> Self {
+        Self::InvalidCount(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        let kind = match &err {
+            Error::Open { source, .. }
+            | Error::Read { source, .. }
+            | Error::Seek { source, .. }
+            | Error::Output(source) => source.kind(),
+            Error::InvalidCount(_)
+            | Error::CountTooLarge
+            | Error::InvalidHeaderFormat(_)
+            | Error::InvalidTimestampFormat(_) => io::ErrorKind::InvalidInput,
+            Error::IsDirectory { .. } => io::ErrorKind::Other,
+            Error::NoFilesRemaining => io::ErrorKind::NotFound,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// The outcome of [`Tail::run`](crate::Tail::run).
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Errors for the sources that could not be tailed, in order.
+    pub errors: Vec<Error>,
+}
+
+impl Summary {
+    /// Returns `true` if every source was tailed.
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the names of the sources that failed.
+    pub fn failed(&self) -> impl Iterator<Item = &str> {
+        self.errors.iter().filter_map(Error::name)
+    }
+}
@@ -0,0 +1,52 @@
+use regex::bytes::Regex;
+
+// Selects records by pattern: a record is kept if it matches any of the
+// `include` patterns, or there are none, and matches none of `exclude`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Filter {
+    pub(crate) include: Vec<Regex>,
+    pub(crate) exclude: Vec<Regex>,
+}
+
+impl Filter {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    // `record` is given without its terminator.
+    pub(crate) fn matches(&self, record: &[u8]) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|regex| regex.is_match(record)))
+            && !self.exclude.iter().any(|regex| regex.is_match(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+    use regex::bytes::Regex;
+
+    #[test]
+    fn test_matches() {
+        // パターンがなければすべて残す
+        let filter = Filter::default();
+        assert!(filter.is_empty());
+        assert!(filter.matches(b"anything"));
+
+        // いずれかのパターンに一致する行を残す
+        let filter = Filter {
+            include: vec![Regex::new("ERROR").unwrap(), Regex::new("WARN").unwrap()],
+            exclude: vec![],
+        };
+        assert!(filter.matches(b"ERROR disk full"));
+        assert!(filter.matches(b"WARN retrying"));
+        assert!(!filter.matches(b"INFO started"));
+
+        // 除外パターンに一致する行は残さない
+        let filter = Filter {
+            include: vec![Regex::new("ERROR").unwrap()],
+            exclude: vec![Regex::new("timeout$").unwrap()],
+        };
+        assert!(filter.matches(b"ERROR disk full"));
+        assert!(!filter.matches(b"ERROR timeout"));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{
+    error::{Error, Result},
+    header::HeaderFormat,
+    printer::Printer,
+    watch::Watcher,
+};
+use clap::ValueEnum;
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Read, Seek, SeekFrom, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FollowMode {
+    Descriptor,
+    Name,
+}
+
+pub(crate) struct FollowedFile {
+    name: String,
+    index: usize,
+    path: Option<String>,
+    file: Option<File>,
+    id: (u64, u64),
+    offset: u64,
+}
+
+impl FollowedFile {
+    pub(crate) fn new(name: &str, index: usize, file: File, offset: u64) -> Result<Self> {
+        let metadata = file.metadata().map_err(|err| Error::open(name, err))?;
+        let id = file_id(&metadata);
+        Ok(Self {
+            name: name.to_string(),
+            index,
+            path: Some(name.to_string()),
+            file: Some(file),
+            id,
+            offset,
+        })
+    }
+
+    pub(crate) fn stdin(index: usize, file: File, offset: u64) -> Result<Self> {
+        let name = "standard input";
+        let metadata = file.metadata().map_err(|err| Error::open(name, err))?;
+        let id = file_id(&metadata);
+        Ok(Self {
+            name: name.to_string(),
+            index,
+            path: None,
+            file: Some(file),
+            id,
+            offset,
+        })
+    }
+
+    pub(crate) fn missing(name: &str, index: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            index,
+            path: Some(name.to_string()),
+            file: None,
+            id: (0, 0),
+            offset: 0,
+        }
+    }
+
+    fn reopen(&mut self, file: File, metadata: &Metadata) {
+        self.file = Some(file);
+        self.id = file_id(metadata);
+        self.offset = 0;
+    }
+}
+
+pub(crate) struct Follower<'a> {
+    files: Vec<FollowedFile>,
+    mode: FollowMode,
+    retry: bool,
+    headers: Option<HeaderFormat>,
+    // The source whose header was printed last.
+    last_printed: Option<usize>,
+    printer: Printer<'a>,
+    err: &'a mut dyn Write,
+    sleep_interval: Duration,
+    use_inotify: bool,
+    pids: Vec<u32>,
+    stop: Option<Arc<AtomicBool>>,
+    buf: Vec<u8>,
+}
+
+impl<'a> Follower<'a> {
+    pub(crate) fn new(
+        files: Vec<FollowedFile>,
+        mode: FollowMode,
+        retry: bool,
+        headers: Option<HeaderFormat>,
+        printer: Printer<'a>,
+        err: &'a mut dyn Write,
+    ) -> Self {
+        Self {
+            files,
+            mode,
+            retry,
+            headers,
+            last_printed: None,
+            printer,
+            err,
+            sleep_interval: Duration::from_secs(1),
+            use_inotify: true,
+            pids: vec![],
+            stop: None,
+            buf: vec![0; 8192],
+        }
+    }
+
+    pub(crate) fn last_printed(mut self, source: Option<usize>) -> Self {
+        self.last_printed = source;
+        self
+    }
+
+    pub(crate) fn sleep_interval(mut self, sleep_interval: Duration) -> Self {
+        self.sleep_interval = sleep_interval;
+        self
+    }
+
+    pub(crate) fn use_inotify(mut self, use_inotify: bool) -> Self {
+        self.use_inotify = use_inotify;
+        self
+    }
+
+    pub(crate) fn pids(mut self, pids: Vec<u32>) -> Self {
+        self.pids = pids;
+        self
+    }
+
+    pub(crate) fn stop(mut self, stop: Option<Arc<AtomicBool>>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    pub(crate) fn run(mut self) -> Result<()> {
+        let res = self.follow();
+        self.printer
+            .finish_all()
+            .and_then(|_| self.printer.flush())
+            .map_err(Error::Output)?;
+        res
+    }
+
+    fn follow(&mut self) -> Result<()> {
+        let paths: Option<Vec<_>> = self.files.iter().map(|f| f.path.clone()).collect();
+        let use_inotify = self.use_inotify && paths.is_some();
+        let names = paths.unwrap_or_else(|| self.files.iter().map(|f| f.name.clone()).collect());
+        let mut watcher = Watcher::new(&names, self.sleep_interval, use_inotify)
+            .wake_periodically(!self.pids.is_empty() || self.stop.is_some());
+        let mut changed: Vec<_> = (0..self.files.len()).collect();
+        loop {
+            if let Some(stop) = &self.stop {
+                if stop.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+            }
+            let pids_exited = !self.pids.is_empty() && !self.pids.iter().any(|&pid| is_alive(pid));
+            if pids_exited {
+                changed = (0..self.files.len()).collect();
+            }
+            for file_num in changed {
+                let check_name =
+                    self.mode == FollowMode::Name || self.files[file_num].file.is_none();
+                if check_name && self.check_name(file_num)? {
+                    watcher.watch_file(file_num);
+                }
+                self.read_appended(file_num)?;
+            }
+            if pids_exited {
+                return Ok(());
+            }
+            if !self.retry && self.files.iter().all(|followed| followed.file.is_none()) {
+                return Err(Error::NoFilesRemaining);
+            }
+            changed = watcher.wait();
+        }
+    }
+
+    fn check_name(&mut self, file_num: usize) -> Result<bool> {
+        let Some(path) = self.files[file_num].path.clone() else {
+            return Ok(false);
+        };
+        if !self.retry && self.files[file_num].file.is_none() {
+            return Ok(false);
+        }
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                if self.files[file_num].file.is_some() {
+                    self.read_appended(file_num)?;
+                    self.reset(file_num)?;
+                    let followed = &mut self.files[file_num];
+                    let message = format!("has become inaccessible: {err}");
+                    notice(&mut self.printer, self.err, &followed.name, &message)?;
+                    followed.file = None;
+                }
+                return Ok(false);
+            }
+        };
+        let followed = &self.files[file_num];
+        let message = match &followed.file {
+            Some(_) if followed.id == file_id(&metadata) => return Ok(false),
+            Some(_) => "has been replaced; following new file",
+            None => "has appeared; following new file",
+        };
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(_) => return Ok(false),
+        };
+        if followed.file.is_some() {
+            self.read_appended(file_num)?;
+            self.reset(file_num)?;
+        }
+        let followed = &mut self.files[file_num];
+        notice(&mut self.printer, self.err, &followed.name, message)?;
+        followed.reopen(file, &metadata);
+        Ok(true)
+    }
+
+    fn read_appended(&mut self, file_num: usize) -> Result<()> {
+        let followed = &mut self.files[file_num];
+        let Some(file) = &mut followed.file else {
+            return Ok(());
+        };
+        let name = &followed.name;
+        let metadata = file.metadata().map_err(|err| Error::read(name, err))?;
+        if metadata.len() < followed.offset {
+            self.printer
+                .reset(followed.index - 1)
+                .map_err(Error::Output)?;
+            notice(&mut self.printer, self.err, name, "file truncated")?;
+            followed.offset = file
+                .seek(SeekFrom::Start(0))
+                .map_err(|err| Error::seek(name, err))?;
+        }
+        loop {
+            let bytes_read = match file.read(&mut self.buf) {
+                Ok(0) => break,
+                Ok(bytes_read) => bytes_read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(Error::read(name, err)),
+            };
+            followed.offset += bytes_read as u64;
+            if let Some(headers) = &self.headers {
+                let source = followed.index - 1;
+                if self.last_printed != Some(source) {
+                    let header = headers.render(name, followed.index, Some(&metadata));
+                    self.printer
+                        .header(source, &header, false)
+                        .map_err(Error::Output)?;
+                    self.last_printed = Some(source);
+                }
+            }
+            self.printer
+                .write(followed.index - 1, &self.buf[..bytes_read])
+                .and_then(|_| self.printer.flush())
+                .map_err(Error::Output)?;
+        }
+        Ok(())
+    }
+
+    // Writes out the incomplete last line of a file that is no longer read,
+    // so that a new file with the same name is counted from the start.
+    fn reset(&mut self, file_num: usize) -> Result<()> {
+        let source = self.files[file_num].index - 1;
+        self.printer.reset(source).map_err(Error::Output)
+    }
+}
+
+// Writes a notice about a followed file to `err`, and to the output as well
+// when it is JSON.
+fn notice(printer: &mut Printer, err: &mut dyn Write, name: &str, message: &str) -> Result<()> {
+    writeln!(err, "{name}: {message}").map_err(Error::Output)?;
+    printer
+        .event("notice", Some(name), message)
+        .map_err(Error::Output)
+}
+
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    let ret = unsafe { libc::kill(pid, 0) };
+    ret == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// Other processes cannot be checked on portably, so they are taken to be
+// running.
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+// Identifies the file behind a name, to tell when it is replaced.
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino())
+}
+
+// Without inode numbers, a replacement is told apart by its creation time.
+#[cfg(not(unix))]
+fn file_id(metadata: &Metadata) -> (u64, u64) {
+    let created = metadata
+        .created()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    (created.as_secs(), created.subsec_nanos().into())
+}
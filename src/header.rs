@@ -0,0 +1,168 @@
+use crate::error::{Error, Result};
+use chrono::{DateTime, Local, SecondsFormat};
+use std::{fs::Metadata, str::FromStr};
+
+/// A template for the header printed before the output of each source.
+///
+/// `{path}`, `{size}`, `{mtime}` and `{index}` are replaced with the name of
+/// the source, its size in bytes, its modification time in RFC 3339 format
+/// and its 1-based position on the command line. `{{` and `}}` stand for
+/// literal braces.
+///
+/// ```
+/// use tailr::HeaderFormat;
+///
+/// let format: HeaderFormat = "--- {index}: {path} ({size} bytes)".parse()?;
+/// # Ok::<(), tailr::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderFormat(Vec<Segment>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Path,
+    Size,
+    Mtime,
+    Index,
+}
+
+impl Default for HeaderFormat {
+    fn default() -> Self {
+        Self(vec![
+            Segment::Text("==> ".to_string()),
+            Segment::Path,
+            Segment::Text(" <==".to_string()),
+        ])
+    }
+}
+
+impl FromStr for HeaderFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidHeaderFormat(reason);
+        let mut segments = vec![];
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        return Err(invalid("unterminated placeholder".to_string()));
+                    };
+                    let segment = match &rest[..end] {
+                        "path" => Segment::Path,
+                        "size" => Segment::Size,
+                        "mtime" => Segment::Mtime,
+                        "index" => Segment::Index,
+                        name => return Err(invalid(format!("unknown placeholder '{{{name}}}'"))),
+                    };
+                    chars = rest[end + 1..].chars();
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(segment);
+                }
+                '}' => return Err(invalid("unmatched '}'".to_string())),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self(segments))
+    }
+}
+
+impl HeaderFormat {
+    // Size and mtime are left empty when the metadata is not available.
+    pub(crate) fn render(&self, path: &str, index: usize, metadata: Option<&Metadata>) -> String {
+        let mut header = String::new();
+        for segment in &self.0 {
+            match segment {
+                Segment::Text(text) => header.push_str(text),
+                Segment::Path => header.push_str(path),
+                Segment::Size => {
+                    if let Some(metadata) = metadata {
+                        header.push_str(&metadata.len().to_string());
+                    }
+                }
+                Segment::Mtime => {
+                    if let Some(mtime) = metadata.and_then(|metadata| metadata.modified().ok()) {
+                        let mtime = DateTime::<Local>::from(mtime);
+                        header.push_str(&mtime.to_rfc3339_opts(SecondsFormat::Secs, true));
+                    }
+                }
+                Segment::Index => header.push_str(&index.to_string()),
+            }
+        }
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeaderFormat;
+    use std::fs;
+
+    #[test]
+    fn test_header_format() {
+        let metadata = fs::metadata("tests/inputs/ten.txt").unwrap();
+
+        // デフォルトはGNU tailと同じ形式
+        let format = HeaderFormat::default();
+        assert_eq!(format.render("ten.txt", 1, None), "==> ten.txt <==");
+
+        // プレースホルダーを置き換える
+        let res = "{index}:{path}:{size}".parse::<HeaderFormat>();
+        assert!(res.is_ok());
+        assert_eq!(
+            res.unwrap().render("ten.txt", 2, Some(&metadata)),
+            "2:ten.txt:49"
+        );
+
+        // 波括弧を2つ重ねるとそのまま出力する
+        let res = "{{{path}}}".parse::<HeaderFormat>();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().render("ten.txt", 1, None), "{ten.txt}");
+
+        // メタデータがない場合は空にする
+        let res = "[{size}]".parse::<HeaderFormat>();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().render("-", 1, None), "[]");
+
+        // 更新日時はRFC 3339形式
+        let res = "{mtime}".parse::<HeaderFormat>();
+        assert!(res.is_ok());
+        let mtime = res.unwrap().render("ten.txt", 1, Some(&metadata));
+        assert!(chrono::DateTime::parse_from_rfc3339(&mtime).is_ok());
+
+        // 不明なプレースホルダーは無効
+        let res = "{name}".parse::<HeaderFormat>();
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "invalid header format: unknown placeholder '{name}'"
+        );
+
+        let res = "{path".parse::<HeaderFormat>();
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "invalid header format: unterminated placeholder"
+        );
+
+        let res = "path}".parse::<HeaderFormat>();
+        assert!(res.is_err());
+    }
+}
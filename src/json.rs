@@ -0,0 +1,96 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) enum Value<'v> {
+    Str(&'v str),
+    Num(u64),
+}
+
+// Writes `fields` as a JSON object on a line of its own.
+pub(crate) fn write_object(out: &mut dyn Write, fields: &[(&str, Value)]) -> io::Result<()> {
+    let mut object = String::from("{");
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            object.push(',');
+        }
+        push_string(&mut object, key);
+        object.push(':');
+        match value {
+            Value::Str(s) => push_string(&mut object, s),
+            Value::Num(n) => {
+                let _ = write!(object, "{n}");
+            }
+        }
+    }
+    object.push_str("}\n");
+    out.write_all(object.as_bytes())
+}
+
+fn push_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' || c == '\u{7f}' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// Encodes `data` in standard base64 with padding.
+pub(crate) fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0_u32, |bits, (i, &byte)| {
+            bits | (u32::from(byte) << (16 - 8 * i))
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, write_object, Value};
+
+    #[test]
+    fn test_write_object() {
+        let mut out = vec![];
+        let res = write_object(
+            &mut out,
+            &[
+                ("text", Value::Str("say \"hi\"\\\t\u{1}")),
+                ("line_no", Value::Num(3)),
+            ],
+        );
+        assert!(res.is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"text\":\"say \\\"hi\\\"\\\\\\t\\u0001\",\"line_no\":3}\n"
+        );
+
+        // 3バイトごとに4文字に変換し、足りない分は「=」で埋める
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"\xff\xfe\x00bar"), "//4AYmFy");
+    }
+}
@@ -1,42 +1,57 @@
 use crate::TakeValue::*;
-use anyhow::Result;
-use clap::Parser;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader, Read, Seek, SeekFrom},
-    str::FromStr,
+use std::str::FromStr;
+
+mod error;
+mod filter;
+mod follow;
+mod header;
+mod json;
+mod printer;
+mod record;
+#[cfg(feature = "tokio")]
+mod stream;
+mod tail;
+mod timestamp;
+mod watch;
+
+#[cfg(feature = "tokio")]
+pub use crate::stream::{follow_lines, LineStream};
+pub use crate::{
+    error::{Error, Result, Summary},
+    follow::FollowMode,
+    header::HeaderFormat,
+    printer::{OutputFormat, Prefix},
+    record::Delimiter,
+    tail::{tail_lines, Headers, Tail, TailLines},
+    timestamp::Timestamp,
 };
 
-#[derive(Debug, Parser)]
-#[command(version, about, long_about = None)]
-pub struct Args {
-    #[arg(value_name = "FILE", help = "Input file(s)", required = true)]
-    files: Vec<String>,
-
-    #[arg(short = 'n', long, help = "Number of lines", default_value = "10")]
-    lines: TakeValue,
-
-    #[arg(short = 'c', long, conflicts_with = "lines", help = "Number of bytes")]
-    bytes: Option<TakeValue>,
-
-    #[arg(short, long, help = "Suppress headers")]
-    quiet: bool,
-}
-
+/// How many lines or bytes to take.
 #[derive(Debug, Clone, PartialEq)]
-enum TakeValue {
+pub enum TakeValue {
+    /// `+0`: everything from the start of the input.
     PlusZero,
+    /// A negative count takes from the end of the input, and a positive
+    /// count starts at that 1-based line or byte.
     TakeNum(i64),
 }
 
 impl FromStr for TakeValue {
-    type Err = std::num::ParseIntError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let num = s
-            .starts_with(['+', '-'])
-            .then(|| s.parse())
-            .unwrap_or_else(|| s.parse().map(i64::wrapping_neg))?;
+    type Err = Error;
+
+    /// Parses a count such as `10`, `-10`, `+10` or `10K`.
+    ///
+    /// A count may end with a multiplier suffix: `b` (512), `K`/`KiB`
+    /// (1024), `KB` (1000), and likewise `M`, `G`, `T`, `P` and `E`. The
+    /// kilo suffixes may also be written with a lowercase `k`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (digits, multiplier) = split_suffix(s);
+        let num = if digits.starts_with(['+', '-']) {
+            digits.parse::<i64>()?
+        } else {
+            -digits.parse::<i64>()?
+        };
+        let num = num.checked_mul(multiplier).ok_or(Error::CountTooLarge)?;
         if num == 0 && s.starts_with('+') {
             Ok(PlusZero)
         } else {
@@ -45,80 +60,27 @@ impl FromStr for TakeValue {
     }
 }
 
-pub fn get_args() -> Result<Args> {
-    Ok(Args::parse())
-}
-
-pub fn run(args: Args) -> Result<()> {
-    let num_files = args.files.len();
-    for (file_num, filename) in args.files.iter().enumerate() {
-        match File::open(filename) {
-            Err(err) => eprintln!("{filename}: {err}"),
-            Ok(file) => {
-                if !args.quiet && num_files > 1 {
-                    println!("{}==> {filename} <==", if file_num > 0 { "\n" } else { "" });
-                }
-                let (total_lines, total_bytes) = count_lines_bytes(filename)?;
-                let file = BufReader::new(file);
-                if let Some(num_bytes) = &args.bytes {
-                    print_bytes(file, num_bytes, total_bytes)?;
-                } else {
-                    print_lines(file, &args.lines, total_lines)?;
-                }
-            }
-        }
-    }
-    Ok(())
-}
-
-fn count_lines_bytes(filename: &str) -> Result<(u64, u64)> {
-    let mut file = BufReader::new(File::open(filename)?);
-    let mut num_lines = 0;
-    let mut num_bytes = 0;
-    let mut buf = vec![];
-    loop {
-        let bytes_read = file.read_until(b'\n', &mut buf)?;
-        if bytes_read == 0 {
-            break;
-        }
-        num_lines += 1;
-        num_bytes += bytes_read as u64;
-        buf.clear();
-    }
-    Ok((num_lines, num_bytes))
-}
-
-fn print_bytes<T>(mut file: T, num_bytes: &TakeValue, total_bytes: u64) -> Result<()>
-where
-    T: Read + Seek,
-{
-    if let Some(start) = get_start_index(num_bytes, total_bytes) {
-        file.seek(SeekFrom::Start(start))?;
-        let mut buf = vec![];
-        file.read_to_end(&mut buf)?;
-        if !buf.is_empty() {
-            print!("{}", String::from_utf8_lossy(&buf));
-        }
+// Splits a multiplier suffix off `s`, returning the rest and the multiplier.
+fn split_suffix(s: &str) -> (&str, i64) {
+    if let Some(digits) = s.strip_suffix('b') {
+        return (digits, 512);
     }
-    Ok(())
-}
-
-fn print_lines(mut file: impl BufRead, num_lines: &TakeValue, total_lines: u64) -> Result<()> {
-    if let Some(start) = get_start_index(num_lines, total_lines) {
-        let mut line_num = 0;
-        let mut buf = vec![];
-        while file.read_until(b'\n', &mut buf)? > 0 {
-            if line_num >= start {
-                print!("{}", String::from_utf8_lossy(&buf));
+    let units: [&[char]; 6] = [&['K', 'k'], &['M'], &['G'], &['T'], &['P'], &['E']];
+    for (exp, unit) in units.into_iter().enumerate() {
+        let exp = exp as u32 + 1;
+        for (suffix, base) in [("iB", 1024_i64), ("B", 1000), ("", 1024)] {
+            if let Some(digits) = s
+                .strip_suffix(suffix)
+                .and_then(|rest| rest.strip_suffix(unit))
+            {
+                return (digits, base.pow(exp));
             }
-            line_num += 1;
-            buf.clear();
         }
     }
-    Ok(())
+    (s, 1)
 }
 
-fn get_start_index(take_val: &TakeValue, total: u64) -> Option<u64> {
+pub(crate) fn get_start_index(take_val: &TakeValue, total: u64) -> Option<u64> {
     match *take_val {
         PlusZero => {
             if total > 0 {
@@ -143,20 +105,9 @@ fn get_start_index(take_val: &TakeValue, total: u64) -> Option<u64> {
 
 #[cfg(test)]
 mod tests {
-    use super::{count_lines_bytes, get_start_index, TakeValue, TakeValue::*};
+    use super::{get_start_index, TakeValue, TakeValue::*};
     use std::str::FromStr;
 
-    #[test]
-    fn test_count_lines_bytes() {
-        let res = count_lines_bytes("tests/inputs/one.txt");
-        assert!(res.is_ok());
-        assert_eq!(res.unwrap(), (1, 24));
-
-        let res = count_lines_bytes("tests/inputs/ten.txt");
-        assert!(res.is_ok());
-        assert_eq!(res.unwrap(), (10, 49));
-    }
-
     #[test]
     fn test_get_start_index() {
         // 空のファイル(0行/バイト)に対して+0を指定したときはNoneを返す
@@ -235,6 +186,86 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), TakeNum(i64::MIN));
 
+        // 接尾辞は倍数として解釈される
+        let res = TakeValue::from_str("2b");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(-1024));
+
+        let res = TakeValue::from_str("+3K");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(3072));
+
+        let res = TakeValue::from_str("-1KiB");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(-1024));
+
+        let res = TakeValue::from_str("1KB");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(-1000));
+
+        // kの小文字も受け付ける
+        let res = TakeValue::from_str("1k");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(-1024));
+
+        let res = TakeValue::from_str("10kB");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(-10_000));
+
+        let res = TakeValue::from_str("+2kiB");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(2048));
+
+        let res = TakeValue::from_str("10M");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(-10 * 1024 * 1024));
+
+        let res = TakeValue::from_str("2GB");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(-2_000_000_000));
+
+        let res = TakeValue::from_str("+0E");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), PlusZero);
+
+        let res = TakeValue::from_str("-8E");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(i64::MIN));
+
+        let res = TakeValue::from_str("8E");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), TakeNum(i64::MIN));
+
+        // 大きすぎる値はエラー
+        let res = TakeValue::from_str("+8E");
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "value too large for defined data type"
+        );
+
+        let res = TakeValue::from_str(&format!("{}K", i64::MAX));
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "value too large for defined data type"
+        );
+
+        let res = TakeValue::from_str(&format!("+{}0", i64::MAX));
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "number too large to fit in target type"
+        );
+
+        // 不明な接尾辞は無効
+        let res = TakeValue::from_str("3X");
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "invalid digit found in string"
+        );
+
         // 浮動小数点数は無効
         let res = TakeValue::from_str("3.14");
         assert!(res.is_err());
@@ -1,6 +1,427 @@
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use regex::bytes::{Regex, RegexBuilder};
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, IsTerminal},
+    time::Duration,
+};
+use tailr::{
+    Delimiter, FollowMode, HeaderFormat, Headers, OutputFormat, Prefix, Summary, Tail, TakeValue,
+    Timestamp,
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[arg(value_name = "FILE", help = "Input file(s)", default_value = "-")]
+    files: Vec<String>,
+
+    #[arg(short = 'n', long, help = "Number of lines", default_value = "10")]
+    lines: TakeValue,
+
+    #[arg(short = 'c', long, conflicts_with = "lines", help = "Number of bytes")]
+    bytes: Option<TakeValue>,
+
+    #[arg(short, long, overrides_with = "verbose", help = "Suppress headers")]
+    quiet: bool,
+
+    #[arg(
+        short,
+        long,
+        overrides_with = "quiet",
+        help = "Always print headers, even for a single file"
+    )]
+    verbose: bool,
+
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "Print headers using TEMPLATE with {path}, {size}, {mtime} and {index}"
+    )]
+    header_format: Option<HeaderFormat>,
+
+    #[arg(long, help = "Start every line with the name of its file")]
+    prefix: bool,
+
+    #[arg(
+        long,
+        value_name = "SEP",
+        default_value = ":",
+        requires = "prefix",
+        help = "With --prefix, separate the name from the line with SEP"
+    )]
+    prefix_separator: String,
+
+    #[arg(
+        long,
+        requires = "prefix",
+        help = "With --prefix, pad names to the same width"
+    )]
+    prefix_align: bool,
+
+    #[arg(long, help = "Start every line with its line number in its file")]
+    line_numbers: bool,
+
+    #[arg(long, help = "Start every line with its byte offset in its file")]
+    offsets: bool,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "rfc3339",
+        help = "While following, start every line with the time it was read: \
+                rfc3339, relative, delta or a strftime template"
+    )]
+    timestamp: Option<Timestamp>,
+
+    #[arg(
+        long,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_value = "auto",
+        default_missing_value = "always",
+        help = "Color headers, prefixes and matches: auto, always or never"
+    )]
+    color: ColorWhen,
+
+    #[arg(
+        long,
+        help = "With color, color lines by severity: ERROR red, WARN yellow"
+    )]
+    severity_colors: bool,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = "text",
+        help = "Write lines, headers and errors as text or as JSON objects"
+    )]
+    output_format: OutputFormat,
+
+    #[arg(long, help = "Replace invalid UTF-8 sequences in the output")]
+    lossy: bool,
+
+    #[arg(short, long, help = "Line delimiter is NUL, not newline")]
+    zero_terminated: bool,
+
+    #[arg(
+        long,
+        value_name = "STRING",
+        value_parser = parse_delimiter,
+        conflicts_with = "zero_terminated",
+        help = "End records with STRING instead of newline (escapes: \\n \\r \\t \\0 \\xHH)"
+    )]
+    delimiter: Option<Delimiter>,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        value_parser = parse_record_start,
+        conflicts_with_all = ["zero_terminated", "delimiter"],
+        help = "Start a new record at each line matching REGEX"
+    )]
+    record_start: Option<Delimiter>,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        value_parser = parse_entries,
+        conflicts_with_all = ["zero_terminated", "delimiter", "record_start"],
+        help = "Count multi-line log entries, each starting at a line matching REGEX \
+                (default: a line starting with a timestamp)"
+    )]
+    entries: Option<Delimiter>,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        conflicts_with = "bytes",
+        help = "Only count and print lines matching REGEX (repeatable)"
+    )]
+    grep: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        conflicts_with = "bytes",
+        help = "Skip lines matching REGEX before counting (repeatable)"
+    )]
+    exclude: Vec<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "Match --grep and --exclude patterns ignoring case"
+    )]
+    ignore_case: bool,
+
+    #[arg(
+        short,
+        long,
+        value_name = "HOW",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "descriptor",
+        help = "Output appended data as the file grows"
+    )]
+    follow: Option<FollowMode>,
+
+    #[arg(short = 'F', help = "Same as --follow=name --retry")]
+    follow_name_retry: bool,
+
+    #[arg(long, help = "Keep trying to open a file if it is inaccessible")]
+    retry: bool,
+
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        value_parser = parse_sleep_interval,
+        default_value = "1.0",
+        help = "With polling, sleep for N seconds between iterations"
+    )]
+    sleep_interval: Duration,
+
+    #[arg(long, help = "Poll files for changes instead of using inotify")]
+    disable_inotify: bool,
+
+    #[arg(
+        long,
+        value_name = "PID",
+        help = "With -f, terminate after process PID dies"
+    )]
+    pid: Vec<u32>,
+
+    #[arg(long, help = "Do not accept the obsolete -N and +N option forms")]
+    posix: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorWhen {
+    // With `auto`, colors are used when stdout is a terminal and NO_COLOR
+    // is not set.
+    fn enabled(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                io::stdout().is_terminal()
+                    && env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+            }
+        }
+    }
+}
+
+fn parse_sleep_interval(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s
+        .parse()
+        .map_err(|_| format!("invalid number of seconds: '{s}'"))?;
+    Duration::try_from_secs_f64(secs).map_err(|_| format!("invalid number of seconds: '{s}'"))
+}
+
+// Parses a record delimiter, expanding the escapes \n, \r, \t, \0, \\ and
+// \xHH.
+fn parse_delimiter(s: &str) -> Result<Delimiter, String> {
+    let invalid = || format!("invalid escape in delimiter: '{s}'");
+    let mut bytes = vec![];
+    let mut iter = s.bytes();
+    while let Some(byte) = iter.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        bytes.push(match iter.next() {
+            Some(b'n') => b'\n',
+            Some(b'r') => b'\r',
+            Some(b't') => b'\t',
+            Some(b'0') => b'\0',
+            Some(b'\\') => b'\\',
+            Some(b'x') => {
+                let hex: Vec<u8> = iter.by_ref().take(2).collect();
+                if hex.len() != 2 || !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return Err(invalid());
+                }
+                u8::from_str_radix(std::str::from_utf8(&hex).unwrap_or_default(), 16)
+                    .map_err(|_| invalid())?
+            }
+            _ => return Err(invalid()),
+        });
+    }
+    if bytes.is_empty() {
+        return Err("the delimiter must not be empty".to_string());
+    }
+    Ok(Delimiter::Terminator(bytes))
+}
+
+fn parse_record_start(s: &str) -> Result<Delimiter, String> {
+    Regex::new(s)
+        .map(Delimiter::RecordStart)
+        .map_err(|err| err.to_string())
+}
+
+fn parse_entries(s: &str) -> Result<Delimiter, String> {
+    if s.is_empty() {
+        Ok(Delimiter::log_entries())
+    } else {
+        parse_record_start(s)
+    }
+}
+
+fn get_args() -> Result<Args> {
+    Ok(Args::parse_from(expand_obsolete(env::args_os().collect())))
+}
+
+// Rewrites an obsolete `-N[bcl][f]` or `+N[bcl][f]` first argument into the
+// equivalent options. Like GNU tail, this is only done when it is followed
+// by at most one file.
+fn expand_obsolete(mut args: Vec<OsString>) -> Vec<OsString> {
+    let is_option = |arg: &OsString| arg.len() > 1 && arg.to_string_lossy().starts_with('-');
+    if args.len() > 3
+        || (args.len() == 3 && is_option(&args[2]))
+        || args.iter().any(|arg| arg == "--posix")
+    {
+        return args;
+    }
+    if let Some(options) = args.get(1).and_then(|arg| parse_obsolete(arg.to_str()?)) {
+        args.splice(1..2, options.into_iter().map(OsString::from));
+    }
+    args
+}
+
+fn parse_obsolete(arg: &str) -> Option<Vec<String>> {
+    let sign = arg.chars().next().filter(|&c| c == '-' || c == '+')?;
+    // `-` is standard input, and `-c` needs a value in the standard form.
+    if arg == "-" || arg == "-c" {
+        return None;
+    }
+    let rest = &arg[1..];
+    let (digits, rest) = rest.split_at(
+        rest.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len()),
+    );
+    let (option, suffix, rest) = match rest.chars().next() {
+        Some('b') => ("--bytes", "b", &rest[1..]),
+        Some('c') => ("--bytes", "", &rest[1..]),
+        Some('l') => ("--lines", "", &rest[1..]),
+        _ => ("--lines", "", rest),
+    };
+    let (follow, rest) = match rest.strip_prefix('f') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    if !rest.is_empty() {
+        return None;
+    }
+    let digits = if digits.is_empty() { "10" } else { digits };
+    let mut options = vec![format!("{option}={sign}{digits}{suffix}")];
+    if follow {
+        options.push("--follow".to_string());
+    }
+    Some(options)
+}
+
+fn run(args: Args) -> Result<Summary> {
+    let (follow, retry) = if args.follow_name_retry {
+        (Some(FollowMode::Name), true)
+    } else {
+        (args.follow, args.retry)
+    };
+    match (follow, retry) {
+        (None, true) => {
+            eprintln!("warning: --retry ignored; --retry is useful only when following")
+        }
+        (Some(FollowMode::Descriptor), true) => {
+            eprintln!("warning: --retry only effective for the initial open")
+        }
+        _ => {}
+    }
+    if follow.is_none() && !args.pid.is_empty() {
+        eprintln!("warning: PID ignored; --pid=PID is useful only when following");
+    }
+    #[cfg(not(unix))]
+    if follow.is_some() && !args.pid.is_empty() {
+        eprintln!("warning: --pid is not supported on this system");
+    }
+    if follow.is_none() && args.timestamp.is_some() {
+        eprintln!("warning: --timestamp ignored; --timestamp is useful only when following");
+    }
+    let mut tail = Tail::new()
+        .sources(args.files)
+        .lines(args.lines)
+        .headers(if args.quiet {
+            Headers::Never
+        } else if args.verbose {
+            Headers::Always
+        } else {
+            Headers::Auto
+        })
+        .line_numbers(args.line_numbers)
+        .offsets(args.offsets)
+        .color(args.color.enabled())
+        .severity_colors(args.severity_colors)
+        .output_format(args.output_format)
+        .lossy(args.lossy)
+        .zero_terminated(args.zero_terminated)
+        .retry(retry)
+        .sleep_interval(args.sleep_interval)
+        .use_inotify(!args.disable_inotify)
+        .pids(args.pid);
+    if let Some(delimiter) = args.delimiter.or(args.record_start).or(args.entries) {
+        tail = tail.delimiter(delimiter);
+    }
+    if let Some(header_format) = args.header_format {
+        tail = tail.header_format(header_format);
+    }
+    let build_regex = |pattern: &String| {
+        RegexBuilder::new(pattern)
+            .case_insensitive(args.ignore_case)
+            .build()
+    };
+    for pattern in &args.grep {
+        tail = tail.grep(build_regex(pattern)?);
+    }
+    for pattern in &args.exclude {
+        tail = tail.exclude(build_regex(pattern)?);
+    }
+    if let Some(timestamp) = args.timestamp {
+        tail = tail.timestamp(timestamp);
+    }
+    if args.prefix {
+        tail = tail.prefix(Prefix {
+            separator: args.prefix_separator,
+            align: args.prefix_align,
+        });
+    }
+    if let Some(num_bytes) = args.bytes {
+        tail = tail.bytes(num_bytes);
+    }
+    if let Some(mode) = follow {
+        tail = tail.follow(mode);
+    }
+    Ok(tail.run(io::stdout().lock(), io::stderr())?)
+}
+
 fn main() {
-    if let Err(err) = tailr::get_args().and_then(tailr::run) {
-        eprintln!("{err}");
-        std::process::exit(1);
+    match get_args().and_then(run) {
+        Ok(summary) if summary.is_success() => {}
+        Ok(_) => std::process::exit(1),
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
     }
 }
@@ -0,0 +1,773 @@
+use crate::{
+    filter::Filter,
+    json::{self, Value},
+    record::Delimiter,
+    timestamp::Timestamp,
+};
+use chrono::Local;
+use clap::ValueEnum;
+use regex::bytes::Regex;
+use std::{
+    fmt,
+    io::{self, Write},
+    mem,
+    time::{Duration, Instant},
+};
+
+const RESET: &str = "\x1b[0m";
+const HEADER_STYLE: &str = "\x1b[1m";
+const MATCH_STYLE: &str = "\x1b[1;31m";
+// Decorations are colored like those of grep.
+const NAME_STYLE: &str = "\x1b[35m";
+const SEPARATOR_STYLE: &str = "\x1b[36m";
+const LINE_NUMBER_STYLE: &str = "\x1b[32m";
+const OFFSET_STYLE: &str = "\x1b[32m";
+// Lines mentioning these levels are colored, most severe first.
+const SEVERITIES: [(&str, &str); 2] = [
+    (r"\b(?:FATAL|CRITICAL|ERROR)\b", "\x1b[31m"),
+    (r"\bWARN(?:ING)?\b", "\x1b[33m"),
+];
+
+/// How lines, headers and errors are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// The lines as they are read, with optional decorations.
+    #[default]
+    Text,
+    /// One JSON object per line, header and error, each with a `type` of
+    /// `line`, `header`, `error` or `notice`.
+    ///
+    /// Lines have the fields `file`, `line_no`, `offset`, `text` and `ts`,
+    /// the time they were written. A line that is not valid UTF-8 has
+    /// `text_base64` instead of `text`.
+    Json,
+}
+
+/// Options for starting every output line with the name of its source, like
+/// `grep -H`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefix {
+    /// Written between the name and the line.
+    pub separator: String,
+    /// Pads names to the width of the longest one.
+    pub align: bool,
+}
+
+impl Default for Prefix {
+    fn default() -> Self {
+        Self {
+            separator: ":".to_string(),
+            align: false,
+        }
+    }
+}
+
+// Writes the output of every source, splitting it into lines when they
+// need to be decorated. Sources are numbered by their position on the
+// command line.
+pub(crate) struct Printer<'a> {
+    out: &'a mut dyn Write,
+    terminator: Vec<u8>,
+    record_start: Option<Regex>,
+    names: Vec<String>,
+    prefix: Option<Prefix>,
+    // Written after each decoration, colored if need be.
+    separator: String,
+    line_numbers: bool,
+    offsets: bool,
+    timestamp: Option<Timestamp>,
+    filter: Filter,
+    format: OutputFormat,
+    color: bool,
+    highlight: Vec<Regex>,
+    severity: Vec<(Regex, &'static str)>,
+    // When stamping started and when the last line was stamped.
+    started: Instant,
+    last_stamped: Instant,
+    width: usize,
+    sources: Vec<Source>,
+}
+
+#[derive(Default)]
+struct Source {
+    // An incomplete line that has not been written yet.
+    pending: Vec<u8>,
+    // The end of the skipped data, in case it holds part of a terminator.
+    skipped: Vec<u8>,
+    position: Position,
+    // The lines of a record held back until it is complete, when records
+    // start at a pattern.
+    record: Vec<u8>,
+    // Whether the current record was written or dropped, once that is
+    // decided. Lines continuing it go the same way.
+    matched: Option<bool>,
+}
+
+// The number of lines and bytes before the pending data of a source.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Position {
+    line: u64,
+    offset: u64,
+}
+
+impl<'a> Printer<'a> {
+    pub(crate) fn new(out: &'a mut dyn Write, names: Vec<String>, delimiter: &Delimiter) -> Self {
+        let (terminator, record_start) = match delimiter {
+            Delimiter::Terminator(term) => (term.clone(), None),
+            Delimiter::RecordStart(regex) => (b"\n".to_vec(), Some(regex.clone())),
+        };
+        Self {
+            out,
+            terminator,
+            record_start,
+            sources: names.iter().map(|_| Source::default()).collect(),
+            names,
+            prefix: None,
+            separator: ":".to_string(),
+            line_numbers: false,
+            offsets: false,
+            timestamp: None,
+            filter: Filter::default(),
+            format: OutputFormat::Text,
+            color: false,
+            highlight: vec![],
+            severity: vec![],
+            started: Instant::now(),
+            last_stamped: Instant::now(),
+            width: 0,
+        }
+    }
+
+    pub(crate) fn prefix(mut self, prefix: Option<Prefix>) -> Self {
+        self.width = match &prefix {
+            Some(prefix) if prefix.align => self
+                .names
+                .iter()
+                .map(|name| name.chars().count())
+                .max()
+                .unwrap_or_default(),
+            _ => 0,
+        };
+        self.prefix = prefix;
+        self.update_separator();
+        self
+    }
+
+    pub(crate) fn line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    pub(crate) fn offsets(mut self, offsets: bool) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    // Stamps every line written from now on with the time it is written.
+    pub(crate) fn timestamp(mut self, timestamp: Option<Timestamp>) -> Self {
+        self.timestamp = timestamp;
+        self.started = Instant::now();
+        self.last_stamped = self.started;
+        self
+    }
+
+    // Drops the records written from now on that do not pass `filter`.
+    pub(crate) fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub(crate) fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    // Colors headers and decorations. Matches and severities are only
+    // colored when `highlight` and `severity` are also set.
+    pub(crate) fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self.update_separator();
+        self
+    }
+
+    fn update_separator(&mut self) {
+        let separator = self
+            .prefix
+            .as_ref()
+            .map_or(":", |prefix| prefix.separator.as_str());
+        self.separator = if self.color {
+            format!("{SEPARATOR_STYLE}{separator}{RESET}")
+        } else {
+            separator.to_string()
+        };
+    }
+
+    pub(crate) fn highlight(mut self, patterns: Vec<Regex>) -> Self {
+        self.highlight = patterns;
+        self
+    }
+
+    pub(crate) fn severity(mut self, severity: bool) -> Self {
+        self.severity = if severity {
+            SEVERITIES
+                .iter()
+                .map(|&(pattern, style)| {
+                    (
+                        Regex::new(pattern).expect("invalid severity pattern"),
+                        style,
+                    )
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        self
+    }
+
+    fn decorated(&self) -> bool {
+        self.prefix.is_some()
+            || self.line_numbers
+            || self.offsets
+            || self.timestamp.is_some()
+            || !self.filter.is_empty()
+            || self.format == OutputFormat::Json
+            || !self.highlight.is_empty()
+            || !self.severity.is_empty()
+    }
+
+    // Whether `skip` needs to see the skipped data rather than its length.
+    pub(crate) fn counts_lines(&self) -> bool {
+        self.line_numbers || self.format == OutputFormat::Json
+    }
+
+    // Moves the position of `source` past data that is not written.
+    pub(crate) fn skip(&mut self, source: usize, data: &[u8]) {
+        if !self.decorated() {
+            return;
+        }
+        let counts_lines = self.counts_lines();
+        let state = &mut self.sources[source];
+        state.position.offset += data.len() as u64;
+        if !counts_lines {
+            return;
+        }
+        let mut skipped = mem::take(&mut state.skipped);
+        skipped.extend_from_slice(data);
+        let mut end = 0;
+        while let Some(pos) = find(&skipped[end..], &self.terminator) {
+            state.position.line += 1;
+            end += pos + self.terminator.len();
+        }
+        let keep = skipped.len() - end;
+        let keep = keep.min(self.terminator.len().saturating_sub(1));
+        skipped.drain(..skipped.len() - keep);
+        state.skipped = skipped;
+    }
+
+    pub(crate) fn skip_bytes(&mut self, source: usize, num_bytes: u64) {
+        self.sources[source].position.offset += num_bytes;
+    }
+
+    pub(crate) fn position(&self, source: usize) -> Position {
+        self.sources[source].position
+    }
+
+    // Moves back or forward to a position taken earlier, for writing
+    // records that were held back while skipping others.
+    pub(crate) fn set_position(&mut self, source: usize, position: Position) {
+        let state = &mut self.sources[source];
+        state.skipped.clear();
+        state.position = position;
+    }
+
+    // Starts counting lines and bytes of `source` from the beginning again,
+    // as when the file is truncated or replaced.
+    pub(crate) fn reset(&mut self, source: usize) -> io::Result<()> {
+        self.finish(source)?;
+        self.sources[source] = Source::default();
+        Ok(())
+    }
+
+    // Writes the header of `source`, after a blank line unless it is the
+    // first one.
+    pub(crate) fn header(&mut self, source: usize, header: &str, first: bool) -> io::Result<()> {
+        let newline = if first { "" } else { "\n" };
+        match self.format {
+            OutputFormat::Json => json::write_object(
+                self.out,
+                &[
+                    ("type", Value::Str("header")),
+                    ("file", Value::Str(&self.names[source])),
+                    ("text", Value::Str(header)),
+                ],
+            ),
+            OutputFormat::Text if self.color => {
+                writeln!(self.out, "{newline}{HEADER_STYLE}{header}{RESET}")
+            }
+            OutputFormat::Text => writeln!(self.out, "{newline}{header}"),
+        }
+    }
+
+    // Writes an error or notice as an object in JSON output. In text
+    // output, these only go to the error stream.
+    pub(crate) fn event(
+        &mut self,
+        kind: &str,
+        file: Option<&str>,
+        message: &str,
+    ) -> io::Result<()> {
+        if self.format != OutputFormat::Json {
+            return Ok(());
+        }
+        let mut fields = vec![("type", Value::Str(kind))];
+        if let Some(file) = file {
+            fields.push(("file", Value::Str(file)));
+        }
+        fields.push(("message", Value::Str(message)));
+        json::write_object(self.out, &fields)
+    }
+
+    // Writes data read from `source`. When lines are decorated, an
+    // incomplete last line is held back until the rest of it is written or
+    // `finish` is called.
+    pub(crate) fn write(&mut self, source: usize, data: &[u8]) -> io::Result<()> {
+        if !self.decorated() {
+            return self.out.write_all(data);
+        }
+        self.sources[source].skipped.clear();
+        let mut pending = mem::take(&mut self.sources[source].pending);
+        let mut search_from = pending
+            .len()
+            .saturating_sub(self.terminator.len().saturating_sub(1));
+        pending.extend_from_slice(data);
+        let mut ends = vec![];
+        while let Some(pos) = find(&pending[search_from..], &self.terminator) {
+            let end = search_from + pos + self.terminator.len();
+            ends.push(end);
+            search_from = end;
+        }
+        self.write_lines(source, &pending, &ends)?;
+        pending.drain(..ends.last().copied().unwrap_or(0));
+        self.sources[source].pending = pending;
+        Ok(())
+    }
+
+    // Writes out the incomplete last line and the held back record of
+    // `source`, if any. With a prefix, the line is terminated so that the
+    // next prefixed line starts on a line of its own.
+    pub(crate) fn finish(&mut self, source: usize) -> io::Result<()> {
+        let mut pending = mem::take(&mut self.sources[source].pending);
+        if !pending.is_empty() {
+            if self.prefix.is_some() {
+                pending.extend_from_slice(&self.terminator);
+            }
+            self.write_lines(source, &pending, &[pending.len()])?;
+        }
+        self.end_record(source)
+    }
+
+    pub(crate) fn finish_all(&mut self) -> io::Result<()> {
+        for source in 0..self.sources.len() {
+            self.finish(source)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    // Writes the lines of `data` ending at `ends`, dropping the records
+    // that do not pass the filter.
+    fn write_lines(&mut self, source: usize, data: &[u8], ends: &[usize]) -> io::Result<()> {
+        let mut start = 0;
+        for &end in ends {
+            self.write_record_line(source, &data[start..end])?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    // When records start at a pattern, the lines of a record are held back
+    // until the next record starts or `finish` is called, so that the
+    // filter sees the whole record.
+    fn write_record_line(&mut self, source: usize, line: &[u8]) -> io::Result<()> {
+        if self.filter.is_empty() {
+            return self.write_line(source, line);
+        }
+        if self.starts_record(line) {
+            self.end_record(source)?;
+            if self.record_start.is_none() {
+                let matched = self.filter.matches(self.content(line));
+                self.sources[source].matched = Some(matched);
+            }
+        }
+        match self.sources[source].matched {
+            Some(true) => self.write_line(source, line),
+            Some(false) => {
+                self.advance(source, line);
+                Ok(())
+            }
+            None => {
+                self.sources[source].record.extend_from_slice(line);
+                Ok(())
+            }
+        }
+    }
+
+    // Writes or drops the record held back for `source`.
+    fn end_record(&mut self, source: usize) -> io::Result<()> {
+        let record = mem::take(&mut self.sources[source].record);
+        self.sources[source].matched = None;
+        if record.is_empty() {
+            return Ok(());
+        }
+        let keep = self.filter.matches(self.content(&record));
+        let mut start = 0;
+        while start < record.len() {
+            let end = find(&record[start..], &self.terminator)
+                .map_or(record.len(), |pos| start + pos + self.terminator.len());
+            let line = &record[start..end];
+            if keep {
+                self.write_line(source, line)?;
+            } else {
+                self.advance(source, line);
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    // Records whether the last record of `source` was written, so that
+    // lines appended to it later go the same way.
+    pub(crate) fn set_matched(&mut self, source: usize, matched: bool) {
+        self.sources[source].matched = Some(matched);
+    }
+
+    fn starts_record(&self, line: &[u8]) -> bool {
+        self.record_start
+            .as_ref()
+            .map_or(true, |regex| regex.is_match(self.content(line)))
+    }
+
+    fn content<'l>(&self, line: &'l [u8]) -> &'l [u8] {
+        line.strip_suffix(self.terminator.as_slice())
+            .unwrap_or(line)
+    }
+
+    // Moves the position of `source` past `line` and returns where the line
+    // starts.
+    fn advance(&mut self, source: usize, line: &[u8]) -> Position {
+        let position = &mut self.sources[source].position;
+        let start = *position;
+        position.offset += line.len() as u64;
+        if line.ends_with(&self.terminator) {
+            position.line += 1;
+        }
+        start
+    }
+
+    fn write_line(&mut self, source: usize, line: &[u8]) -> io::Result<()> {
+        let start = self.advance(source, line);
+        let content = self.content(line);
+        let stamp = self.timestamp.as_ref().map(|timestamp| {
+            let now = Instant::now();
+            let stamp = timestamp.render(Local::now(), now - self.started, now - self.last_stamped);
+            self.last_stamped = now;
+            stamp
+        });
+        if self.format == OutputFormat::Json {
+            return self.write_json(source, start, content, stamp);
+        }
+        if let Some(stamp) = stamp {
+            write!(self.out, "{stamp} ")?;
+        }
+        let separator = &self.separator;
+        if self.prefix.is_some() {
+            let name = &self.names[source];
+            write_decoration(self.out, self.color, NAME_STYLE, name)?;
+            let padding = self.width.saturating_sub(name.chars().count());
+            write!(self.out, "{:padding$}{separator}", "")?;
+        }
+        if self.line_numbers {
+            write_decoration(self.out, self.color, LINE_NUMBER_STYLE, start.line + 1)?;
+            write!(self.out, "{separator}")?;
+        }
+        if self.offsets {
+            write_decoration(self.out, self.color, OFFSET_STYLE, start.offset)?;
+            write!(self.out, "{separator}")?;
+        }
+        if self.highlight.is_empty() && self.severity.is_empty() {
+            return self.out.write_all(line);
+        }
+        self.write_styled(content, &line[content.len()..])
+    }
+
+    fn write_json(
+        &mut self,
+        source: usize,
+        start: Position,
+        content: &[u8],
+        stamp: Option<String>,
+    ) -> io::Result<()> {
+        let stamp = stamp.unwrap_or_else(|| {
+            Timestamp::default().render(Local::now(), Duration::ZERO, Duration::ZERO)
+        });
+        let encoded;
+        let text = match std::str::from_utf8(content) {
+            Ok(text) => ("text", Value::Str(text)),
+            Err(_) => {
+                encoded = json::base64(content);
+                ("text_base64", Value::Str(&encoded))
+            }
+        };
+        json::write_object(
+            self.out,
+            &[
+                ("type", Value::Str("line")),
+                ("file", Value::Str(&self.names[source])),
+                ("line_no", Value::Num(start.line + 1)),
+                ("offset", Value::Num(start.offset)),
+                text,
+                ("ts", Value::Str(&stamp)),
+            ],
+        )
+    }
+
+    // Writes a line in the color of its severity, if any, with the matches
+    // of the highlighted patterns in bold red. The terminator is written
+    // after the colors are reset.
+    fn write_styled(&mut self, content: &[u8], terminator: &[u8]) -> io::Result<()> {
+        let style = self
+            .severity
+            .iter()
+            .find(|(regex, _)| regex.is_match(content))
+            .map_or("", |&(_, style)| style);
+        let mut matches: Vec<_> = self
+            .highlight
+            .iter()
+            .flat_map(|regex| regex.find_iter(content).map(|m| m.range()))
+            .collect();
+        matches.sort_unstable_by_key(|range| range.start);
+        write!(self.out, "{style}")?;
+        let mut pos = 0;
+        for range in matches {
+            if range.end <= pos {
+                continue;
+            }
+            let start = range.start.max(pos);
+            self.out.write_all(&content[pos..start])?;
+            write!(self.out, "{MATCH_STYLE}")?;
+            self.out.write_all(&content[start..range.end])?;
+            write!(self.out, "{RESET}{style}")?;
+            pos = range.end;
+        }
+        self.out.write_all(&content[pos..])?;
+        if !style.is_empty() {
+            write!(self.out, "{RESET}")?;
+        }
+        self.out.write_all(terminator)
+    }
+}
+
+// Adapts a `Printer` to `Write` for the data of a single source.
+pub(crate) struct SourceWriter<'p, 'a> {
+    printer: &'p mut Printer<'a>,
+    source: usize,
+}
+
+impl<'p, 'a> SourceWriter<'p, 'a> {
+    pub(crate) fn new(printer: &'p mut Printer<'a>, source: usize) -> Self {
+        Self { printer, source }
+    }
+
+    pub(crate) fn counts_lines(&self) -> bool {
+        self.printer.counts_lines()
+    }
+
+    pub(crate) fn skip(&mut self, data: &[u8]) {
+        self.printer.skip(self.source, data);
+    }
+
+    pub(crate) fn skip_bytes(&mut self, num_bytes: u64) {
+        self.printer.skip_bytes(self.source, num_bytes);
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        self.printer.position(self.source)
+    }
+
+    pub(crate) fn set_position(&mut self, position: Position) {
+        self.printer.set_position(self.source, position);
+    }
+
+    pub(crate) fn set_matched(&mut self, matched: bool) {
+        self.printer.set_matched(self.source, matched);
+    }
+}
+
+impl Write for SourceWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.printer.write(self.source, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.printer.flush()
+    }
+}
+
+fn write_decoration(
+    out: &mut dyn Write,
+    color: bool,
+    style: &str,
+    value: impl fmt::Display,
+) -> io::Result<()> {
+    if color {
+        write!(out, "{style}{value}{RESET}")
+    } else {
+        write!(out, "{value}")
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutputFormat, Prefix, Printer};
+    use crate::{record::Delimiter, timestamp::Timestamp};
+
+    #[test]
+    fn test_prefix() {
+        let names = vec!["a.log".to_string(), "long.log".to_string()];
+        let delimiter = Delimiter::default();
+
+        // 各行の先頭にファイル名を付ける
+        let mut out = vec![];
+        let mut printer =
+            Printer::new(&mut out, names.clone(), &delimiter).prefix(Some(Prefix::default()));
+        assert!(printer.write(0, b"one\ntw").is_ok());
+        assert!(printer.write(1, b"three\n").is_ok());
+        assert!(printer.write(0, b"o\nfour").is_ok());
+        assert!(printer.finish_all().is_ok());
+        assert_eq!(out, b"a.log:one\nlong.log:three\na.log:two\na.log:four\n");
+
+        // ファイル名の幅をそろえる
+        let mut out = vec![];
+        let prefix = Prefix {
+            separator: " | ".to_string(),
+            align: true,
+        };
+        let mut printer = Printer::new(&mut out, names.clone(), &delimiter).prefix(Some(prefix));
+        assert!(printer.write(0, b"one\n").is_ok());
+        assert!(printer.write(1, b"two\n").is_ok());
+        assert_eq!(out, b"a.log    | one\nlong.log | two\n");
+
+        // 複数バイトの区切り文字が書き込みをまたいでも1行として扱う
+        let mut out = vec![];
+        let delimiter = Delimiter::Terminator(b"\r\n".to_vec());
+        let mut printer =
+            Printer::new(&mut out, names.clone(), &delimiter).prefix(Some(Prefix::default()));
+        assert!(printer.write(0, b"one\r").is_ok());
+        assert!(printer.write(0, b"\ntwo\r\n").is_ok());
+        assert_eq!(out, b"a.log:one\r\na.log:two\r\n");
+
+        // 装飾しない場合はそのまま書き込む
+        let mut out = vec![];
+        let mut printer = Printer::new(&mut out, names, &Delimiter::default());
+        assert!(printer.write(0, b"one\ntw").is_ok());
+        assert_eq!(out, b"one\ntw");
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let names = vec!["a.log".to_string(), "long.log".to_string()];
+
+        // 行番号とオフセットを付ける
+        let mut out = vec![];
+        let mut printer = Printer::new(&mut out, names.clone(), &Delimiter::default())
+            .line_numbers(true)
+            .offsets(true);
+        printer.skip(0, b"one\ntwo\nth");
+        printer.skip(0, b"ree\n");
+        assert!(printer.write(0, b"four\nfive\n").is_ok());
+        assert_eq!(out, b"4:14:four\n5:19:five\n");
+
+        // 複数バイトの区切り文字がスキップをまたいでも1行として数える
+        let mut out = vec![];
+        let delimiter = Delimiter::Terminator(b"\r\n".to_vec());
+        let mut printer = Printer::new(&mut out, names, &delimiter).line_numbers(true);
+        printer.skip(0, b"one\r");
+        printer.skip(0, b"\ntwo\r");
+        printer.skip(0, b"\n");
+        assert!(printer.write(0, b"three\r\n").is_ok());
+        assert_eq!(out, b"3:three\r\n");
+    }
+
+    #[test]
+    fn test_color() {
+        let names = vec!["a.log".to_string(), "long.log".to_string()];
+
+        // 一致した部分と重要度を色付けする
+        let mut out = vec![];
+        let mut printer = Printer::new(&mut out, names.clone(), &Delimiter::default())
+            .color(true)
+            .highlight(vec![regex::bytes::Regex::new("disk|full").unwrap()])
+            .severity(true);
+        assert!(printer.header(0, "==> a.log <==", false).is_ok());
+        assert!(printer
+            .write(0, b"ERROR disk full\nINFO disk ok\nWARN slow\n")
+            .is_ok());
+        assert_eq!(
+            out,
+            b"\n\x1b[1m==> a.log <==\x1b[0m\n\
+              \x1b[31mERROR \x1b[1;31mdisk\x1b[0m\x1b[31m \x1b[1;31mfull\x1b[0m\x1b[31m\x1b[0m\n\
+              INFO \x1b[1;31mdisk\x1b[0m ok\n\
+              \x1b[33mWARN slow\x1b[0m\n"
+        );
+
+        // ファイル名、行番号、オフセットと区切り文字も色付けする
+        let mut out = vec![];
+        let mut printer = Printer::new(&mut out, names, &Delimiter::default())
+            .color(true)
+            .prefix(Some(Prefix::default()))
+            .line_numbers(true)
+            .offsets(true);
+        assert!(printer.write(1, b"one\n").is_ok());
+        assert_eq!(
+            out,
+            b"\x1b[35mlong.log\x1b[0m\x1b[36m:\x1b[0m\
+              \x1b[32m1\x1b[0m\x1b[36m:\x1b[0m\
+              \x1b[32m0\x1b[0m\x1b[36m:\x1b[0mone\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let names = vec!["a.log".to_string(), "long.log".to_string()];
+
+        // JSONでは行とヘッダーをオブジェクトとして書き込む
+        let mut out = vec![];
+        let mut printer = Printer::new(&mut out, names, &Delimiter::default())
+            .format(OutputFormat::Json)
+            .timestamp(Some(Timestamp::Clock("now".to_string())));
+        assert!(printer.header(1, "==> long.log <==", true).is_ok());
+        printer.skip(1, b"one\n");
+        assert!(printer.write(1, b"t\"wo\n\xff\n").is_ok());
+        assert!(printer.event("error", Some("a.log"), "gone").is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"type\":\"header\",\"file\":\"long.log\",\"text\":\"==> long.log <==\"}\n\
+             {\"type\":\"line\",\"file\":\"long.log\",\"line_no\":2,\"offset\":4,\"text\":\"t\\\"wo\",\"ts\":\"now\"}\n\
+             {\"type\":\"line\",\"file\":\"long.log\",\"line_no\":3,\"offset\":9,\"text_base64\":\"/w==\",\"ts\":\"now\"}\n\
+             {\"type\":\"error\",\"file\":\"a.log\",\"message\":\"gone\"}\n"
+        );
+    }
+}
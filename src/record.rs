@@ -0,0 +1,170 @@
+use regex::bytes::Regex;
+use std::{
+    io::{self, BufRead},
+    mem,
+};
+
+// Matches a line that starts with a timestamp such as `2024-01-02 03:04:05`,
+// `2024/01/02 03:04`, `Jan  2 03:04:05` or `03:04:05`, optionally inside
+// brackets.
+const TIMESTAMP: &str = r"^\[?(?:\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}|\d{4}/\d{2}/\d{2} \d{2}:\d{2}|[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}|\d{2}:\d{2}:\d{2})";
+
+/// How the input is split into the records counted by [`Tail::lines`].
+///
+/// [`Tail::lines`]: crate::Tail::lines
+#[derive(Debug, Clone)]
+pub enum Delimiter {
+    /// Each record ends with this byte string.
+    Terminator(Vec<u8>),
+    /// Each record starts at a line matching this pattern and takes in
+    /// the lines up to the next match.
+    RecordStart(Regex),
+}
+
+impl Default for Delimiter {
+    fn default() -> Self {
+        Self::Terminator(b"\n".to_vec())
+    }
+}
+
+impl Delimiter {
+    /// Splits the input into log entries, each starting at a line that
+    /// begins with a timestamp. Indented lines and other lines without a
+    /// timestamp, such as stack traces, belong to the preceding entry.
+    pub fn log_entries() -> Self {
+        Self::RecordStart(Regex::new(TIMESTAMP).expect("invalid timestamp pattern"))
+    }
+
+    // Returns `record` without its terminator.
+    pub(crate) fn content<'r>(&self, record: &'r [u8]) -> &'r [u8] {
+        let term = match self {
+            Self::Terminator(term) => term.as_slice(),
+            Self::RecordStart(_) => b"\n",
+        };
+        record.strip_suffix(term).unwrap_or(record)
+    }
+
+    // Returns the terminator if it is a single byte, in which case records
+    // can be found by scanning backwards from the end of a file.
+    pub(crate) fn as_byte(&self) -> Option<u8> {
+        match self {
+            Self::Terminator(term) if term.len() == 1 => Some(term[0]),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) struct Records<'a, R> {
+    reader: R,
+    delimiter: &'a Delimiter,
+    pending: Vec<u8>,
+}
+
+impl<'a, R: BufRead> Records<'a, R> {
+    pub(crate) fn new(reader: R, delimiter: &'a Delimiter) -> Self {
+        Self {
+            reader,
+            delimiter,
+            pending: vec![],
+        }
+    }
+
+    // Appends the next record to `buf` and returns its length, which is 0
+    // at the end of the input.
+    pub(crate) fn read_record(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let start = buf.len();
+        match self.delimiter {
+            Delimiter::Terminator(term) => {
+                let Some(&last) = term.last() else {
+                    return self.reader.read_to_end(buf);
+                };
+                while self.reader.read_until(last, buf)? > 0 && !buf[start..].ends_with(term) {}
+            }
+            Delimiter::RecordStart(regex) => {
+                buf.append(&mut self.pending);
+                let mut line = vec![];
+                while self.reader.read_until(b'\n', &mut line)? > 0 {
+                    let text = line.strip_suffix(b"\n").unwrap_or(&line);
+                    if buf.len() > start && regex.is_match(text) {
+                        self.pending = mem::take(&mut line);
+                        break;
+                    }
+                    buf.append(&mut line);
+                }
+            }
+        }
+        Ok(buf.len() - start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Delimiter, Records};
+    use regex::bytes::Regex;
+
+    fn collect_records(input: &[u8], delimiter: &Delimiter) -> Vec<Vec<u8>> {
+        let mut records = Records::new(input, delimiter);
+        let mut result = vec![];
+        loop {
+            let mut buf = vec![];
+            let res = records.read_record(&mut buf);
+            assert!(res.is_ok());
+            if res.unwrap() == 0 {
+                return result;
+            }
+            result.push(buf);
+        }
+    }
+
+    #[test]
+    fn test_read_record() {
+        // 1バイトの区切り文字
+        let delimiter = Delimiter::Terminator(b"\x1e".to_vec());
+        assert_eq!(
+            collect_records(b"one\x1etwo\nthree\x1efour", &delimiter),
+            [&b"one\x1e"[..], b"two\nthree\x1e", b"four"]
+        );
+
+        // 複数バイトの区切り文字
+        let delimiter = Delimiter::Terminator(b"\r\n".to_vec());
+        assert_eq!(
+            collect_records(b"one\ntwo\r\nthree\r\n", &delimiter),
+            [&b"one\ntwo\r\n"[..], b"three\r\n"]
+        );
+
+        // 区切り文字が空の場合は全体を1つのレコードとする
+        let delimiter = Delimiter::Terminator(vec![]);
+        assert_eq!(collect_records(b"one\ntwo", &delimiter), [b"one\ntwo"]);
+
+        // 一致する行から次に一致する行の手前までを1つのレコードとする
+        let delimiter = Delimiter::RecordStart(Regex::new("^---$").unwrap());
+        assert_eq!(
+            collect_records(b"a: 1\n---\nb: 2\nc: 3\n---\nd: 4", &delimiter),
+            [&b"a: 1\n"[..], b"---\nb: 2\nc: 3\n", b"---\nd: 4"]
+        );
+
+        // 空の入力からは何も返さない
+        assert!(collect_records(b"", &delimiter).is_empty());
+
+        // タイムスタンプで始まらない行は直前のエントリーに含める
+        let delimiter = Delimiter::log_entries();
+        let input = b"2024-01-02 03:04:05 INFO start\n\
+                      2024-01-02 03:04:06 ERROR failed\n\
+                      java.lang.Exception: boom\n\
+                      \tat Main.main(Main.java:3)\n\
+                      [2024-01-02T03:04:07] INFO retry\n\
+                      Jan  2 03:04:08 host sshd[1]: ok\n\
+                      \x20   03:04:09 indented\n";
+        assert_eq!(
+            collect_records(input, &delimiter),
+            [
+                &b"2024-01-02 03:04:05 INFO start\n"[..],
+                b"2024-01-02 03:04:06 ERROR failed\n\
+                  java.lang.Exception: boom\n\
+                  \tat Main.main(Main.java:3)\n",
+                b"[2024-01-02T03:04:07] INFO retry\n",
+                b"Jan  2 03:04:08 host sshd[1]: ok\n\x20   03:04:09 indented\n",
+            ]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{Error, FollowMode, Tail, TakeValue};
+use futures_core::Stream;
+use std::{
+    io::{self, Write},
+    mem,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    thread,
+};
+use tokio::sync::mpsc;
+
+const CHANNEL_CAPACITY: usize = 64;
+
+pub(crate) type ItemSender = mpsc::Sender<Result<Vec<u8>, Error>>;
+
+/// Follows `path` by name, yielding the lines selected by `num_lines` and
+/// then every line appended to it, across rotations.
+///
+/// ```no_run
+/// use tailr::{follow_lines, TakeValue};
+///
+/// # async fn example() {
+/// let lines = follow_lines("app.log", TakeValue::TakeNum(-10));
+/// # }
+/// ```
+pub fn follow_lines(path: impl Into<String>, num_lines: TakeValue) -> LineStream {
+    Tail::new()
+        .source(path)
+        .lines(num_lines)
+        .follow(FollowMode::Name)
+        .retry(true)
+        .into_stream()
+}
+
+impl Tail {
+    /// Runs this tail on a background thread and yields its output as
+    /// lines, without their trailing newlines. Errors on sources are
+    /// yielded as they happen.
+    ///
+    /// The thread stops shortly after the stream is dropped.
+    pub fn into_stream(self) -> LineStream {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let stop = Arc::new(AtomicBool::new(false));
+        let tail = self.stop(Arc::clone(&stop)).errors(tx.clone());
+        thread::spawn(move || {
+            let mut sender = LineSender {
+                tx: tx.clone(),
+                partial: vec![],
+            };
+            let res = tail.write_to(&mut sender);
+            if !sender.partial.is_empty() {
+                let _ = tx.blocking_send(Ok(mem::take(&mut sender.partial)));
+            }
+            if let Err(err) = res {
+                let _ = tx.blocking_send(Err(err));
+            }
+        });
+        LineStream { rx, stop }
+    }
+}
+
+/// Stream returned by [`follow_lines`] and [`Tail::into_stream`].
+#[derive(Debug)]
+pub struct LineStream {
+    rx: mpsc::Receiver<Result<Vec<u8>, Error>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Stream for LineStream {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Drop for LineStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+struct LineSender {
+    tx: ItemSender,
+    partial: Vec<u8>,
+}
+
+impl Write for LineSender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(pos) = rest.iter().position(|&byte| byte == b'\n') {
+            self.partial.extend_from_slice(&rest[..pos]);
+            self.tx
+                .blocking_send(Ok(mem::take(&mut self.partial)))
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+            rest = &rest[pos + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{follow_lines, LineStream};
+    use crate::{Error, TakeValue::*};
+    use futures_core::Stream;
+    use std::{
+        fs::{self, OpenOptions},
+        future,
+        io::{self, Write},
+        pin::Pin,
+        time::Duration,
+    };
+
+    async fn next_line(stream: &mut LineStream) -> Option<Result<Vec<u8>, Error>> {
+        let next = future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx));
+        tokio::time::timeout(Duration::from_secs(5), next)
+            .await
+            .expect("timed out waiting for a line")
+    }
+
+    #[tokio::test]
+    async fn test_follow_lines() {
+        let path = std::env::temp_dir().join(format!("tailr-stream-{}", std::process::id()));
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        let mut stream = follow_lines(path.to_str().unwrap(), TakeNum(-2));
+
+        // 最初に末尾の行を返す
+        assert_eq!(next_line(&mut stream).await.unwrap().unwrap(), b"two");
+        assert_eq!(next_line(&mut stream).await.unwrap().unwrap(), b"three");
+
+        // 追記された行を返す
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"four\nfi").unwrap();
+        file.write_all(b"ve\n").unwrap();
+        assert_eq!(next_line(&mut stream).await.unwrap().unwrap(), b"four");
+        assert_eq!(next_line(&mut stream).await.unwrap().unwrap(), b"five");
+
+        // ローテーションされた後は新しいファイルを読む
+        fs::rename(&path, path.with_extension("1")).unwrap();
+        fs::write(&path, "six\n").unwrap();
+        assert_eq!(next_line(&mut stream).await.unwrap().unwrap(), b"six");
+
+        drop(stream);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(path.with_extension("1")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_follow_lines_error() {
+        // 追跡を続けていてもエラーはすぐに返す
+        let path = std::env::temp_dir().join(format!("tailr-missing-{}", std::process::id()));
+        let mut stream = follow_lines(path.to_str().unwrap(), TakeNum(-2));
+        let res = next_line(&mut stream).await.unwrap();
+        assert!(matches!(
+            res,
+            Err(Error::Open { source, .. }) if source.kind() == io::ErrorKind::NotFound
+        ));
+
+        // 後から作成されたファイルを読む
+        fs::write(&path, "one\n").unwrap();
+        assert_eq!(next_line(&mut stream).await.unwrap().unwrap(), b"one");
+
+        drop(stream);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,1106 @@
+#[cfg(feature = "tokio")]
+use crate::stream::ItemSender;
+use crate::{
+    error::{Error, Result, Summary},
+    filter::Filter,
+    follow::{FollowMode, FollowedFile, Follower},
+    get_start_index,
+    header::HeaderFormat,
+    printer::{OutputFormat, Prefix, Printer, SourceWriter},
+    record::{Delimiter, Records},
+    timestamp::Timestamp,
+    TakeValue::{self, *},
+};
+use regex::bytes::Regex;
+use std::{
+    collections::VecDeque,
+    fs::{File, Metadata},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    mem,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+const BLOCK_SIZE: usize = 8192;
+
+/// When to print a `==> name <==` header before each source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Headers {
+    /// Print headers only when there is more than one source.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Builder for printing the end of one or more files.
+///
+/// ```no_run
+/// use tailr::{Tail, TakeValue};
+///
+/// let mut out = vec![];
+/// let summary = Tail::new()
+///     .source("app.log")
+///     .lines(TakeValue::TakeNum(-20))
+///     .write_to(&mut out)?;
+/// assert!(summary.is_success());
+/// # Ok::<(), tailr::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tail {
+    sources: Vec<String>,
+    lines: TakeValue,
+    bytes: Option<TakeValue>,
+    headers: Headers,
+    header_format: HeaderFormat,
+    prefix: Option<Prefix>,
+    line_numbers: bool,
+    offsets: bool,
+    timestamp: Option<Timestamp>,
+    color: bool,
+    severity_colors: bool,
+    output_format: OutputFormat,
+    lossy: bool,
+    delimiter: Delimiter,
+    filter: Filter,
+    follow: Option<FollowMode>,
+    retry: bool,
+    sleep_interval: Duration,
+    use_inotify: bool,
+    pids: Vec<u32>,
+    stop: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "tokio")]
+    errors: Option<ItemSender>,
+}
+
+impl Default for Tail {
+    fn default() -> Self {
+        Self {
+            sources: vec![],
+            lines: TakeNum(-10),
+            bytes: None,
+            headers: Headers::Auto,
+            header_format: HeaderFormat::default(),
+            prefix: None,
+            line_numbers: false,
+            offsets: false,
+            timestamp: None,
+            color: false,
+            severity_colors: false,
+            output_format: OutputFormat::Text,
+            lossy: false,
+            delimiter: Delimiter::default(),
+            filter: Filter::default(),
+            follow: None,
+            retry: false,
+            sleep_interval: Duration::from_secs(1),
+            use_inotify: true,
+            pids: vec![],
+            stop: None,
+            #[cfg(feature = "tokio")]
+            errors: None,
+        }
+    }
+}
+
+impl Tail {
+    /// Creates a builder that prints the last 10 lines of standard input.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file to read; `-` means standard input.
+    pub fn source(mut self, name: impl Into<String>) -> Self {
+        self.sources.push(name.into());
+        self
+    }
+
+    pub fn sources<I>(mut self, names: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.sources.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn lines(mut self, num_lines: TakeValue) -> Self {
+        self.lines = num_lines;
+        self.bytes = None;
+        self
+    }
+
+    pub fn bytes(mut self, num_bytes: TakeValue) -> Self {
+        self.bytes = Some(num_bytes);
+        self
+    }
+
+    pub fn headers(mut self, headers: Headers) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn header_format(mut self, header_format: HeaderFormat) -> Self {
+        self.header_format = header_format;
+        self
+    }
+
+    /// Starts every output line with the name of its source. Headers are
+    /// then only printed with [`Headers::Always`].
+    ///
+    /// When following, a line is written once it is complete. A last line
+    /// without a terminator is terminated.
+    pub fn prefix(mut self, prefix: Prefix) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Starts every output line with its 1-based line number in the source.
+    pub fn line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    /// Starts every output line with the byte offset of its first byte in
+    /// the source.
+    pub fn offsets(mut self, offsets: bool) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    /// Starts every line written while following with the time it was
+    /// read. Lines of the initial tail are not stamped.
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Colors headers, prefixes, positions and the matches of [`Tail::grep`]
+    /// patterns with ANSI escape sequences.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// With [`Tail::color`], also colors lines mentioning `ERROR`, `FATAL`
+    /// or `CRITICAL` red and lines mentioning `WARN` or `WARNING` yellow.
+    pub fn severity_colors(mut self, severity_colors: bool) -> Self {
+        self.severity_colors = severity_colors;
+        self
+    }
+
+    /// Writes lines, headers and errors as JSON objects instead of text.
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Replaces invalid UTF-8 sequences in the output with U+FFFD.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    /// Separates lines with NUL bytes instead of newlines.
+    pub fn zero_terminated(mut self, zero_terminated: bool) -> Self {
+        self.delimiter =
+            Delimiter::Terminator(if zero_terminated { b"\0" } else { b"\n" }.to_vec());
+        self
+    }
+
+    /// Sets how the input is split into the records counted by
+    /// [`Tail::lines`].
+    pub fn delimiter(mut self, delimiter: Delimiter) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Only keeps the records matching `pattern`, or any of the patterns
+    /// when called more than once. Records are filtered before they are
+    /// counted, so the last 20 lines are the last 20 matching lines.
+    ///
+    /// Filters do not apply to [`Tail::bytes`].
+    pub fn grep(mut self, pattern: Regex) -> Self {
+        self.filter.include.push(pattern);
+        self
+    }
+
+    /// Drops the records matching `pattern` before they are counted.
+    pub fn exclude(mut self, pattern: Regex) -> Self {
+        self.filter.exclude.push(pattern);
+        self
+    }
+
+    /// Keeps writing data appended to the sources after the initial tail.
+    pub fn follow(mut self, mode: FollowMode) -> Self {
+        self.follow = Some(mode);
+        self
+    }
+
+    /// Keeps trying to open sources that are inaccessible while following.
+    pub fn retry(mut self, retry: bool) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn sleep_interval(mut self, sleep_interval: Duration) -> Self {
+        self.sleep_interval = sleep_interval;
+        self
+    }
+
+    pub fn use_inotify(mut self, use_inotify: bool) -> Self {
+        self.use_inotify = use_inotify;
+        self
+    }
+
+    /// Stops following once all of the given processes have exited.
+    ///
+    /// Only Unix systems can check on other processes. Elsewhere, following
+    /// goes on as if they were still running.
+    pub fn pids(mut self, pids: impl IntoIterator<Item = u32>) -> Self {
+        self.pids.extend(pids);
+        self
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    // Sends errors on sources as they happen instead of collecting them
+    // in the summary.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn errors(mut self, errors: ItemSender) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Writes the selected data to `out` and returns a summary of the
+    /// sources that failed.
+    ///
+    /// Sources that cannot be read are skipped; only a failure to write to
+    /// `out` stops the run early.
+    pub fn write_to(&self, out: impl Write) -> Result<Summary> {
+        self.run(out, io::sink())
+    }
+
+    /// Like [`Tail::write_to`], but also writes error messages and follow
+    /// notices such as `file truncated` to `err` as they happen.
+    pub fn run(&self, mut out: impl Write, mut err: impl Write) -> Result<Summary> {
+        if self.lossy {
+            let mut lossy = LossyWriter::new(&mut out);
+            let summary = self.run_to(&mut lossy, &mut err)?;
+            lossy.finish().map_err(Error::Output)?;
+            Ok(summary)
+        } else {
+            self.run_to(&mut out, &mut err)
+        }
+    }
+
+    fn run_to(&self, out: &mut dyn Write, mut err: &mut dyn Write) -> Result<Summary> {
+        let sources = if self.sources.is_empty() {
+            vec!["-".to_string()]
+        } else {
+            self.sources.clone()
+        };
+        let print_headers = match self.headers {
+            Headers::Auto => sources.len() > 1 && self.prefix.is_none(),
+            Headers::Always => true,
+            Headers::Never => false,
+        };
+        let names = sources
+            .iter()
+            .map(|filename| display_name(filename).to_string())
+            .collect();
+        let mut printer = Printer::new(out, names, &self.delimiter)
+            .prefix(self.prefix.clone())
+            .line_numbers(self.line_numbers)
+            .offsets(self.offsets)
+            .format(self.output_format)
+            .color(self.color);
+        if self.color {
+            printer = printer
+                .highlight(self.filter.include.clone())
+                .severity(self.severity_colors);
+        }
+        let mut summary = Summary::default();
+        let mut followed = vec![];
+        let mut last_header = None;
+        for (file_num, filename) in sources.iter().enumerate() {
+            let is_stdin = filename == "-";
+            let name = display_name(filename);
+            let (input, metadata) = match open(filename) {
+                Ok(opened) => opened,
+                Err(error) => {
+                    let error = Error::open(name, error);
+                    report(&mut printer, &mut err, &error)?;
+                    if self.follow.is_some() && self.retry && !is_stdin {
+                        followed.push(FollowedFile::missing(filename, file_num + 1));
+                    }
+                    self.record(&mut summary, error);
+                    continue;
+                }
+            };
+            if print_headers {
+                let header = self
+                    .header_format
+                    .render(name, file_num + 1, Some(&metadata));
+                printer
+                    .header(file_num, &header, file_num == 0)
+                    .map_err(Error::Output)?;
+                last_header = Some(file_num);
+            }
+            let out = &mut SourceWriter::new(&mut printer, file_num);
+            let res = match input {
+                Input::File(file) => {
+                    let mut file = BufReader::new(file);
+                    self.print_tail(&mut file, out).and_then(|_| {
+                        let pos = file.stream_position().map_err(Failure::Seek)?;
+                        let mut file = file.into_inner();
+                        file.seek(SeekFrom::Start(pos)).map_err(Failure::Seek)?;
+                        Ok((Some(file), pos))
+                    })
+                }
+                Input::Stream(stream) => self
+                    .print_tail_stream(&mut BufReader::new(stream), out)
+                    .map(|_| (None, 0)),
+                Input::Directory => {
+                    let error = Error::IsDirectory {
+                        name: name.to_string(),
+                    };
+                    report(&mut printer, &mut err, &error)?;
+                    self.record(&mut summary, error);
+                    continue;
+                }
+            };
+            let mut is_followed = false;
+            match res {
+                Ok((Some(file), pos)) if self.follow.is_some() => {
+                    if !is_stdin {
+                        followed.push(FollowedFile::new(filename, file_num + 1, file, pos)?);
+                        is_followed = true;
+                    } else if self.follow == Some(FollowMode::Descriptor) {
+                        followed.push(FollowedFile::stdin(file_num + 1, file, pos)?);
+                        is_followed = true;
+                    } else {
+                        let message = "warning: cannot follow standard input by name";
+                        writeln!(err, "{message}").map_err(Error::Output)?;
+                        printer
+                            .event("notice", Some(name), message)
+                            .map_err(Error::Output)?;
+                    }
+                }
+                Ok(_) => {}
+                Err(Failure::Output(error)) => return Err(Error::Output(error)),
+                Err(failure) => {
+                    let error = failure.into_error(name);
+                    report(&mut printer, &mut err, &error)?;
+                    self.record(&mut summary, error);
+                }
+            }
+            if !is_followed {
+                printer.finish(file_num).map_err(Error::Output)?;
+            }
+        }
+        printer.flush().map_err(Error::Output)?;
+        if let Some(mode) = self.follow {
+            if !followed.is_empty() {
+                let headers = print_headers.then(|| self.header_format.clone());
+                let filter = match self.bytes {
+                    Some(_) => Filter::default(),
+                    None => self.filter.clone(),
+                };
+                let printer = printer.timestamp(self.timestamp.clone()).filter(filter);
+                Follower::new(followed, mode, self.retry, headers, printer, &mut err)
+                    .last_printed(last_header)
+                    .sleep_interval(self.sleep_interval)
+                    .use_inotify(self.use_inotify)
+                    .pids(self.pids.clone())
+                    .stop(self.stop.clone())
+                    .run()?;
+            }
+        }
+        Ok(summary)
+    }
+
+    fn record(&self, summary: &mut Summary, error: Error) {
+        #[cfg(feature = "tokio")]
+        if let Some(errors) = &self.errors {
+            let _ = errors.blocking_send(Err(error));
+            return;
+        }
+        summary.errors.push(error);
+    }
+
+    fn print_tail<T>(&self, file: &mut T, out: &mut SourceWriter) -> Result<(), Failure>
+    where
+        T: BufRead + Seek,
+    {
+        if let Some(num_bytes) = &self.bytes {
+            let total_bytes = file.seek(SeekFrom::End(0)).map_err(Failure::Seek)?;
+            print_bytes(file, num_bytes, total_bytes, out)
+        } else if !self.filter.is_empty() {
+            file.rewind().map_err(Failure::Seek)?;
+            print_matching(file, &self.lines, &self.delimiter, &self.filter, out)
+        } else {
+            print_lines(file, &self.lines, &self.delimiter, out)
+        }
+    }
+
+    fn print_tail_stream(
+        &self,
+        file: &mut impl BufRead,
+        out: &mut SourceWriter,
+    ) -> Result<(), Failure> {
+        if let Some(num_bytes) = &self.bytes {
+            print_bytes_stream(file, num_bytes, out)
+        } else if !self.filter.is_empty() {
+            print_matching(file, &self.lines, &self.delimiter, &self.filter, out)
+        } else {
+            print_lines_stream(file, &self.lines, &self.delimiter, out)
+        }
+    }
+}
+
+// Keeps errors on the source apart from errors on the output, which stop
+// the whole run.
+enum Failure {
+    Read(io::Error),
+    Seek(io::Error),
+    Output(io::Error),
+}
+
+impl Failure {
+    fn into_error(self, name: &str) -> Error {
+        match self {
+            Self::Read(err) => Error::read(name, err),
+            Self::Seek(err) => Error::seek(name, err),
+            Self::Output(err) => Error::Output(err),
+        }
+    }
+}
+
+// Writes an error on a source to `err`, and to the output as well when it
+// is JSON.
+fn report(printer: &mut Printer, err: &mut impl Write, error: &Error) -> Result<()> {
+    writeln!(err, "{error}").map_err(Error::Output)?;
+    printer
+        .event("error", error.name(), &error.reason())
+        .map_err(Error::Output)
+}
+
+// Replaces invalid UTF-8 sequences with U+FFFD. A character split across
+// writes is held back until the rest of it is written.
+struct LossyWriter<W> {
+    out: W,
+    partial: Vec<u8>,
+}
+
+impl<W: Write> LossyWriter<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            partial: vec![],
+        }
+    }
+
+    // Writes out an incomplete character left at the end of the output.
+    fn finish(&mut self) -> io::Result<()> {
+        if !mem::take(&mut self.partial).is_empty() {
+            self.out.write_all("\u{FFFD}".as_bytes())?;
+        }
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Write for LossyWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = mem::take(&mut self.partial);
+        data.extend_from_slice(buf);
+        let mut rest = &data[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    self.out.write_all(valid.as_bytes())?;
+                    break;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    self.out.write_all(valid)?;
+                    match err.error_len() {
+                        Some(len) => {
+                            self.out.write_all("\u{FFFD}".as_bytes())?;
+                            rest = &invalid[len..];
+                        }
+                        None => {
+                            self.partial = invalid.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+fn display_name(filename: &str) -> &str {
+    if filename == "-" {
+        "standard input"
+    } else {
+        filename
+    }
+}
+
+enum Input {
+    File(File),
+    Stream(File),
+    Directory,
+}
+
+// Duplicates the handle of standard input, so that it can be read, sought
+// and followed like any other file.
+#[cfg(unix)]
+fn stdin_file() -> io::Result<File> {
+    use std::os::fd::AsFd;
+    Ok(File::from(io::stdin().as_fd().try_clone_to_owned()?))
+}
+
+#[cfg(windows)]
+fn stdin_file() -> io::Result<File> {
+    use std::os::windows::io::AsHandle;
+    Ok(File::from(io::stdin().as_handle().try_clone_to_owned()?))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn stdin_file() -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "standard input cannot be read on this system",
+    ))
+}
+
+fn open(filename: &str) -> io::Result<(Input, Metadata)> {
+    let file = if filename == "-" {
+        stdin_file()?
+    } else {
+        File::open(filename)?
+    };
+    let metadata = file.metadata()?;
+    let input = if metadata.is_file() {
+        Input::File(file)
+    } else if metadata.is_dir() {
+        Input::Directory
+    } else {
+        Input::Stream(file)
+    };
+    Ok((input, metadata))
+}
+
+fn copy(file: &mut impl Read, out: &mut dyn Write) -> Result<(), Failure> {
+    let mut buf = [0; BLOCK_SIZE];
+    loop {
+        let bytes_read = match file.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(bytes_read) => bytes_read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Failure::Read(err)),
+        };
+        out.write_all(&buf[..bytes_read]).map_err(Failure::Output)?;
+    }
+}
+
+fn skip(file: &mut impl Read, num_bytes: u64, out: &mut SourceWriter) -> Result<(), Failure> {
+    let mut file = file.take(num_bytes);
+    let mut buf = [0; BLOCK_SIZE];
+    loop {
+        let bytes_read = match file.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(bytes_read) => bytes_read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Failure::Read(err)),
+        };
+        out.skip(&buf[..bytes_read]);
+    }
+}
+
+fn print_bytes_stream(
+    file: &mut impl BufRead,
+    num_bytes: &TakeValue,
+    out: &mut SourceWriter,
+) -> Result<(), Failure> {
+    match *num_bytes {
+        TakeNum(num) if num < 0 => {
+            let limit = num.unsigned_abs();
+            let mut ring = VecDeque::new();
+            loop {
+                let chunk = file.fill_buf().map_err(Failure::Read)?;
+                if chunk.is_empty() {
+                    break;
+                }
+                ring.extend(chunk);
+                let len = chunk.len();
+                file.consume(len);
+                let excess = (ring.len() as u64).saturating_sub(limit) as usize;
+                if excess > 0 {
+                    let dropped: Vec<u8> = ring.drain(..excess).collect();
+                    out.skip(&dropped);
+                }
+            }
+            out.write_all(ring.make_contiguous())
+                .map_err(Failure::Output)?;
+        }
+        _ => {
+            if let Some(start) = get_start_index(num_bytes, u64::MAX) {
+                skip(file, start, out)?;
+                copy(file, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_lines_stream(
+    file: &mut impl BufRead,
+    num_lines: &TakeValue,
+    delimiter: &Delimiter,
+    out: &mut SourceWriter,
+) -> Result<(), Failure> {
+    let mut records = Records::new(file, delimiter);
+    match *num_lines {
+        TakeNum(num) if num < 0 => {
+            let limit = num.unsigned_abs();
+            let mut ring = VecDeque::new();
+            let mut buf = vec![];
+            while records.read_record(&mut buf).map_err(Failure::Read)? > 0 {
+                ring.push_back(mem::take(&mut buf));
+                if ring.len() as u64 > limit {
+                    buf = ring.pop_front().unwrap_or_default();
+                    out.skip(&buf);
+                    buf.clear();
+                }
+            }
+            for line in ring {
+                out.write_all(&line).map_err(Failure::Output)?;
+            }
+        }
+        _ => {
+            if let Some(start) = get_start_index(num_lines, u64::MAX) {
+                let mut line_num = 0;
+                let mut buf = vec![];
+                while records.read_record(&mut buf).map_err(Failure::Read)? > 0 {
+                    if line_num >= start {
+                        out.write_all(&buf).map_err(Failure::Output)?;
+                    } else {
+                        out.skip(&buf);
+                    }
+                    line_num += 1;
+                    buf.clear();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Like `print_lines_stream`, but only counts and prints the records that
+// pass `filter`. The input is read once, keeping the last matching records
+// with their positions. Whether the last record was printed is passed on,
+// so that following continues it the same way.
+fn print_matching(
+    file: &mut impl BufRead,
+    num_lines: &TakeValue,
+    delimiter: &Delimiter,
+    filter: &Filter,
+    out: &mut SourceWriter,
+) -> Result<(), Failure> {
+    let mut records = Records::new(file, delimiter);
+    let mut buf = vec![];
+    match *num_lines {
+        TakeNum(num) if num < 0 => {
+            let limit = num.unsigned_abs();
+            let mut ring = VecDeque::new();
+            let mut last_matched = None;
+            loop {
+                let position = out.position();
+                if records.read_record(&mut buf).map_err(Failure::Read)? == 0 {
+                    break;
+                }
+                out.skip(&buf);
+                let matched = filter.matches(delimiter.content(&buf));
+                last_matched = Some(matched);
+                if matched {
+                    ring.push_back((position, mem::take(&mut buf)));
+                    if ring.len() as u64 > limit {
+                        buf = ring.pop_front().map(|(_, line)| line).unwrap_or_default();
+                    }
+                }
+                buf.clear();
+            }
+            let end = out.position();
+            for (position, line) in ring {
+                out.set_position(position);
+                out.write_all(&line).map_err(Failure::Output)?;
+            }
+            // Writing the last record already ends at the end of the
+            // input, and may leave it pending.
+            match last_matched {
+                Some(true) if limit > 0 => out.set_matched(true),
+                Some(_) => {
+                    out.set_position(end);
+                    out.set_matched(false);
+                }
+                None => out.set_position(end),
+            }
+        }
+        _ => {
+            let start = get_start_index(num_lines, u64::MAX).unwrap_or(u64::MAX);
+            let mut matched = 0;
+            while records.read_record(&mut buf).map_err(Failure::Read)? > 0 {
+                let printed = if !filter.matches(delimiter.content(&buf)) {
+                    false
+                } else {
+                    matched += 1;
+                    matched > start
+                };
+                if printed {
+                    out.write_all(&buf).map_err(Failure::Output)?;
+                } else {
+                    out.skip(&buf);
+                }
+                out.set_matched(printed);
+                buf.clear();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_bytes<T>(
+    file: &mut T,
+    num_bytes: &TakeValue,
+    total_bytes: u64,
+    out: &mut SourceWriter,
+) -> Result<(), Failure>
+where
+    T: Read + Seek,
+{
+    match get_start_index(num_bytes, total_bytes) {
+        Some(start) => print_from(file, start, out),
+        None => seek_output(file, total_bytes, out),
+    }
+}
+
+fn print_lines<T>(
+    file: &mut T,
+    num_lines: &TakeValue,
+    delimiter: &Delimiter,
+    out: &mut SourceWriter,
+) -> Result<(), Failure>
+where
+    T: BufRead + Seek,
+{
+    match *num_lines {
+        TakeNum(0) => {
+            let total_bytes = file.seek(SeekFrom::End(0)).map_err(Failure::Seek)?;
+            seek_output(file, total_bytes, out)
+        }
+        TakeNum(num) if num < 0 => match delimiter.as_byte() {
+            Some(byte) => {
+                let start =
+                    find_lines_start(file, num.unsigned_abs(), byte).map_err(Failure::Read)?;
+                print_from(file, start, out)
+            }
+            // Other delimiters cannot be searched for backwards, so read
+            // the file from the start instead.
+            None => {
+                file.rewind().map_err(Failure::Seek)?;
+                print_lines_stream(file, num_lines, delimiter, out)
+            }
+        },
+        _ => {
+            file.rewind().map_err(Failure::Seek)?;
+            print_lines_stream(file, num_lines, delimiter, out)
+        }
+    }
+}
+
+fn print_from<T>(file: &mut T, start: u64, out: &mut SourceWriter) -> Result<(), Failure>
+where
+    T: Read + Seek,
+{
+    seek_output(file, start, out)?;
+    copy(file, out)
+}
+
+// Moves to `start`, reading the data before it when the printer needs to
+// count the lines in it.
+fn seek_output<T>(file: &mut T, start: u64, out: &mut SourceWriter) -> Result<(), Failure>
+where
+    T: Read + Seek,
+{
+    if out.counts_lines() {
+        file.rewind().map_err(Failure::Seek)?;
+        skip(file, start, out)
+    } else {
+        file.seek(SeekFrom::Start(start)).map_err(Failure::Seek)?;
+        out.skip_bytes(start);
+        Ok(())
+    }
+}
+
+/// Returns the lines of `reader` selected by `num_lines`, without their
+/// trailing newlines.
+///
+/// ```
+/// use std::io::Cursor;
+/// use tailr::{tail_lines, TakeValue};
+///
+/// let input = Cursor::new("one\ntwo\nthree\n");
+/// let lines = tail_lines(input, &TakeValue::TakeNum(-2))?.collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(lines, [b"two".to_vec(), b"three".to_vec()]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn tail_lines<R>(mut reader: R, num_lines: &TakeValue) -> io::Result<TailLines<R>>
+where
+    R: Read + Seek,
+{
+    let (start, skip) = match *num_lines {
+        TakeNum(0) => (reader.seek(SeekFrom::End(0))?, 0),
+        TakeNum(num) if num < 0 => (find_lines_start(&mut reader, num.unsigned_abs(), b'\n')?, 0),
+        _ => (0, get_start_index(num_lines, u64::MAX).unwrap_or_default()),
+    };
+    reader.seek(SeekFrom::Start(start))?;
+    Ok(TailLines {
+        reader: BufReader::new(reader),
+        skip,
+    })
+}
+
+/// Iterator returned by [`tail_lines`].
+#[derive(Debug)]
+pub struct TailLines<R> {
+    reader: BufReader<R>,
+    skip: u64,
+}
+
+impl<R: Read> Iterator for TailLines<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut buf = vec![];
+            match self.reader.read_until(b'\n', &mut buf) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+            if self.skip > 0 {
+                self.skip -= 1;
+                continue;
+            }
+            if buf.last() == Some(&b'\n') {
+                buf.pop();
+            }
+            return Some(Ok(buf));
+        }
+    }
+}
+
+fn find_lines_start<T>(file: &mut T, num_lines: u64, delimiter: u8) -> io::Result<u64>
+where
+    T: Read + Seek,
+{
+    let total_bytes = file.seek(SeekFrom::End(0))?;
+    let mut buf = vec![0; BLOCK_SIZE];
+    let mut pos = total_bytes;
+    let mut found = 0;
+    while pos > 0 {
+        let block_len = pos.min(BLOCK_SIZE as u64) as usize;
+        pos -= block_len as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buf[..block_len])?;
+        for (i, _) in buf[..block_len]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, &byte)| byte == delimiter)
+        {
+            let line_start = pos + i as u64 + 1;
+            if line_start == total_bytes {
+                continue;
+            }
+            found += 1;
+            if found == num_lines {
+                return Ok(line_start);
+            }
+        }
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_lines_start, tail_lines, Error, Headers, Tail, BLOCK_SIZE};
+    use crate::TakeValue::*;
+    use std::{
+        fs::{self, File},
+        io::{Cursor, ErrorKind},
+    };
+
+    fn collect_lines(input: &[u8], num_lines: crate::TakeValue) -> Vec<Vec<u8>> {
+        let res = tail_lines(Cursor::new(input), &num_lines);
+        assert!(res.is_ok());
+        let lines: Result<Vec<_>, _> = res.unwrap().collect();
+        assert!(lines.is_ok());
+        lines.unwrap()
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        let input = b"one\ntwo\nthree";
+
+        // 末尾から指定した行数を返す
+        assert_eq!(collect_lines(input, TakeNum(-2)), [&b"two"[..], b"three"]);
+        assert_eq!(
+            collect_lines(input, TakeNum(-20)),
+            [&b"one"[..], b"two", b"three"]
+        );
+
+        // 正の数は指定した行から最後までを返す
+        assert_eq!(collect_lines(input, TakeNum(2)), [&b"two"[..], b"three"]);
+        assert_eq!(
+            collect_lines(input, PlusZero),
+            [&b"one"[..], b"two", b"three"]
+        );
+        assert!(collect_lines(input, TakeNum(4)).is_empty());
+
+        // 0行を指定した場合は何も返さない
+        assert!(collect_lines(input, TakeNum(0)).is_empty());
+
+        // 空の入力からは何も返さない
+        assert!(collect_lines(b"", TakeNum(-3)).is_empty());
+
+        // 空行も1行として返す
+        assert_eq!(collect_lines(b"a\n\n", TakeNum(-1)), [&b""[..]]);
+
+        let file = File::open("tests/inputs/ten.txt").unwrap();
+        let res = tail_lines(file, &TakeNum(-3));
+        assert!(res.is_ok());
+        let lines: Vec<_> = res.unwrap().map(Result::unwrap).collect();
+        assert_eq!(lines, [&b"eight"[..], b"nine", b"ten"]);
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut out = vec![];
+        let res = Tail::new()
+            .source("tests/inputs/ten.txt")
+            .lines(TakeNum(-3))
+            .write_to(&mut out);
+        assert!(res.is_ok());
+        assert!(res.unwrap().is_success());
+        assert_eq!(out, fs::read("tests/expected/ten.txt.n3.out").unwrap());
+
+        let mut out = vec![];
+        let res = Tail::new()
+            .sources(["tests/inputs/one.txt", "tests/inputs/two.txt"])
+            .bytes(TakeNum(3))
+            .write_to(&mut out);
+        assert!(res.is_ok());
+        assert!(res.unwrap().is_success());
+        assert_eq!(
+            out,
+            b"==> tests/inputs/one.txt <==\nne line, four word\xc5\x9b.\n\n\
+              ==> tests/inputs/two.txt <==\no lines.\nFour words.\n"
+        );
+
+        // ヘッダーを表示しない
+        let mut out = vec![];
+        let res = Tail::new()
+            .sources(["tests/inputs/one.txt", "tests/inputs/two.txt"])
+            .lines(TakeNum(-1))
+            .headers(Headers::Never)
+            .write_to(&mut out);
+        assert!(res.is_ok());
+        assert_eq!(out, b"\xc3\x96ne line, four word\xc5\x9b.\nFour words.\n");
+    }
+
+    #[test]
+    fn test_write_to_file_error() {
+        // 開けないファイルはエラーとして返し、残りのファイルの処理を続ける
+        let mut out = vec![];
+        let res = Tail::new()
+            .sources(["tests/inputs/missing.txt", "tests/inputs/one.txt"])
+            .headers(Headers::Never)
+            .write_to(&mut out);
+        assert!(res.is_ok());
+        let summary = res.unwrap();
+        assert!(!summary.is_success());
+        assert_eq!(
+            summary.failed().collect::<Vec<_>>(),
+            ["tests/inputs/missing.txt"]
+        );
+        assert!(matches!(
+            &summary.errors[0],
+            Error::Open { source, .. } if source.kind() == ErrorKind::NotFound
+        ));
+        assert_eq!(out, fs::read("tests/expected/one.txt.out").unwrap());
+    }
+
+    #[test]
+    fn test_find_lines_start() {
+        let mut file = File::open("tests/inputs/one.txt").unwrap();
+        let res = find_lines_start(&mut file, 1, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 0);
+
+        let mut file = File::open("tests/inputs/ten.txt").unwrap();
+        let res = find_lines_start(&mut file, 1, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 45);
+
+        // ファイルの行数より多い行数を指定した場合はファイルの先頭を返す
+        let res = find_lines_start(&mut file, 10, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 0);
+        let res = find_lines_start(&mut file, 200, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 0);
+
+        // 最終行が改行で終わっていない場合も1行として数える
+        let res = find_lines_start(&mut Cursor::new(b"one\ntwo"), 1, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 4);
+
+        // 空のファイルは0を返す
+        let res = find_lines_start(&mut Cursor::new(b""), 1, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 0);
+
+        // ブロックの境界をまたいで行を探す
+        let mut data = vec![b'a'; BLOCK_SIZE * 2];
+        data[10] = b'\n';
+        data[BLOCK_SIZE + 10] = b'\n';
+        data.push(b'\n');
+        let res = find_lines_start(&mut Cursor::new(&data), 1, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), BLOCK_SIZE as u64 + 11);
+        let res = find_lines_start(&mut Cursor::new(&data), 2, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 11);
+        let res = find_lines_start(&mut Cursor::new(&data), 3, b'\n');
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 0);
+
+        // 区切り文字にNULを指定する
+        let res = find_lines_start(&mut Cursor::new(b"one\0two\nthree\0"), 1, 0);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 4);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::error::{Error, Result};
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Local,
+};
+use std::{str::FromStr, time::Duration};
+
+const RFC3339: &str = "%Y-%m-%dT%H:%M:%S%.3f%:z";
+
+/// How to stamp each line written while following with the time it was
+/// read.
+///
+/// Parsed from `rfc3339`, `relative`, `delta` or a `strftime`-like template
+/// such as `%H:%M:%S`.
+///
+/// ```
+/// use tailr::Timestamp;
+///
+/// let timestamp: Timestamp = "%H:%M:%S%.3f".parse()?;
+/// # Ok::<(), tailr::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Timestamp {
+    /// The local time, formatted with a `strftime`-like template.
+    Clock(String),
+    /// Seconds since following started.
+    Relative,
+    /// Seconds since the previous stamped line.
+    Delta,
+}
+
+impl Default for Timestamp {
+    fn default() -> Self {
+        Self::Clock(RFC3339.to_string())
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "rfc3339" => Ok(Self::default()),
+            "relative" => Ok(Self::Relative),
+            "delta" => Ok(Self::Delta),
+            _ if StrftimeItems::new(s).any(|item| item == Item::Error) => {
+                Err(Error::InvalidTimestampFormat(s.to_string()))
+            }
+            _ => Ok(Self::Clock(s.to_string())),
+        }
+    }
+}
+
+impl Timestamp {
+    pub(crate) fn render(
+        &self,
+        now: DateTime<Local>,
+        since_start: Duration,
+        since_last: Duration,
+    ) -> String {
+        match self {
+            Self::Clock(format) => now.format(format).to_string(),
+            Self::Relative => format!("{:.3}", since_start.as_secs_f64()),
+            Self::Delta => format!("+{:.3}", since_last.as_secs_f64()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamp;
+    use chrono::{Local, TimeZone};
+    use std::time::Duration;
+
+    #[test]
+    fn test_timestamp() {
+        let now = Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let since_start = Duration::from_millis(12_345);
+        let since_last = Duration::from_millis(20);
+
+        // デフォルトはRFC 3339形式
+        let stamp = Timestamp::default().render(now, since_start, since_last);
+        assert!(stamp.starts_with("2024-01-02T03:04:05.000"));
+        assert!(chrono::DateTime::parse_from_rfc3339(&stamp).is_ok());
+
+        // テンプレートで書式を指定する
+        let res = "%H:%M:%S".parse::<Timestamp>();
+        assert!(res.is_ok());
+        assert_eq!(
+            res.unwrap().render(now, since_start, since_last),
+            "03:04:05"
+        );
+
+        // 開始からの経過時間と前の行からの経過時間
+        let res = "relative".parse::<Timestamp>();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().render(now, since_start, since_last), "12.345");
+
+        let res = "delta".parse::<Timestamp>();
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().render(now, since_start, since_last), "+0.020");
+
+        // 不明な書式指定子は無効
+        let res = "%Q".parse::<Timestamp>();
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "invalid timestamp format '%Q'"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use std::{thread, time::Duration};
+
+pub(crate) struct Watcher {
+    inner: Inner,
+    num_files: usize,
+    sleep_interval: Duration,
+    timeout: Option<Duration>,
+}
+
+enum Inner {
+    #[cfg(target_os = "linux")]
+    Inotify(inotify_watcher::InotifyWatcher),
+    Poll,
+}
+
+impl Watcher {
+    pub(crate) fn new(names: &[String], sleep_interval: Duration, use_inotify: bool) -> Self {
+        Self {
+            inner: Inner::new(names, use_inotify),
+            num_files: names.len(),
+            sleep_interval,
+            timeout: None,
+        }
+    }
+
+    pub(crate) fn wake_periodically(mut self, wake_periodically: bool) -> Self {
+        self.timeout = wake_periodically.then_some(self.sleep_interval);
+        self
+    }
+
+    pub(crate) fn watch_file(&mut self, file_num: usize) {
+        #[cfg(target_os = "linux")]
+        if let Inner::Inotify(watcher) = &mut self.inner {
+            if watcher.watch_file(file_num).is_err() {
+                self.inner = Inner::Poll;
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = file_num;
+    }
+
+    // Blocks until some files may have changed and returns their indexes.
+    pub(crate) fn wait(&mut self) -> Vec<usize> {
+        #[cfg(target_os = "linux")]
+        if let Inner::Inotify(watcher) = &mut self.inner {
+            match watcher.wait(self.timeout) {
+                Ok(Some(changed)) => return changed,
+                Ok(None) => return (0..self.num_files).collect(),
+                // Fall back to polling rather than give up on following.
+                Err(_) => self.inner = Inner::Poll,
+            }
+        }
+        thread::sleep(self.sleep_interval);
+        (0..self.num_files).collect()
+    }
+}
+
+impl Inner {
+    #[cfg(target_os = "linux")]
+    fn new(names: &[String], use_inotify: bool) -> Self {
+        if use_inotify {
+            if let Ok(watcher) = inotify_watcher::InotifyWatcher::new(names) {
+                return Self::Inotify(watcher);
+            }
+        }
+        Self::Poll
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn new(_names: &[String], _use_inotify: bool) -> Self {
+        Self::Poll
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify_watcher {
+    use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+    use std::{
+        collections::HashMap,
+        ffi::{CString, OsString},
+        io,
+        mem::MaybeUninit,
+        os::unix::{ffi::OsStrExt, io::AsRawFd},
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    // Filesystems on which changes made by other hosts are not reported.
+    const REMOTE_FS_MAGICS: &[u32] = &[
+        0x0102_1997, // V9FS
+        0x0000_6969, // NFS
+        0x0000_517b, // SMB
+        0x6573_5546, // FUSE
+        0x7375_7245, // CODA
+        0x5346_414f, // AFS
+        0x00c3_6400, // CEPH
+        0xfe53_4d42, // SMB2
+        0xff53_4d42, // CIFS
+    ];
+
+    pub(super) struct InotifyWatcher {
+        inotify: Inotify,
+        paths: Vec<PathBuf>,
+        files: HashMap<WatchDescriptor, Vec<usize>>,
+        dirs: HashMap<WatchDescriptor, Vec<(OsString, usize)>>,
+        buf: Vec<u8>,
+    }
+
+    impl InotifyWatcher {
+        pub(super) fn new(names: &[String]) -> io::Result<Self> {
+            let mut watcher = Self {
+                inotify: Inotify::init()?,
+                paths: names.iter().map(PathBuf::from).collect(),
+                files: HashMap::new(),
+                dirs: HashMap::new(),
+                buf: vec![0; 4096],
+            };
+            for file_num in 0..names.len() {
+                let path = &watcher.paths[file_num];
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                if is_remote(dir)? {
+                    return Err(io::ErrorKind::Unsupported.into());
+                }
+                let Some(file_name) = path.file_name().map(OsString::from) else {
+                    return Err(io::ErrorKind::InvalidInput.into());
+                };
+                let wd = watcher.inotify.watches().add(
+                    dir,
+                    WatchMask::CREATE
+                        | WatchMask::DELETE
+                        | WatchMask::MOVED_FROM
+                        | WatchMask::MOVED_TO,
+                )?;
+                watcher
+                    .dirs
+                    .entry(wd)
+                    .or_default()
+                    .push((file_name, file_num));
+                watcher.watch_file(file_num)?;
+            }
+            Ok(watcher)
+        }
+
+        pub(super) fn watch_file(&mut self, file_num: usize) -> io::Result<()> {
+            let mask = WatchMask::MODIFY
+                | WatchMask::ATTRIB
+                | WatchMask::DELETE_SELF
+                | WatchMask::MOVE_SELF;
+            match self.inotify.watches().add(&self.paths[file_num], mask) {
+                Ok(wd) => {
+                    let file_nums = self.files.entry(wd).or_default();
+                    if !file_nums.contains(&file_num) {
+                        file_nums.push(file_num);
+                    }
+                    Ok(())
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(err) => Err(err),
+            }
+        }
+
+        // Returns the files that may have changed, or `None` if every file
+        // needs to be checked again.
+        pub(super) fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Option<Vec<usize>>> {
+            let mut fds = libc::pollfd {
+                fd: self.inotify.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout = timeout.map_or(-1, |timeout| timeout.as_millis() as libc::c_int);
+            if unsafe { libc::poll(&mut fds, 1, timeout) } < 0 {
+                let err = io::Error::last_os_error();
+                return match err.kind() {
+                    io::ErrorKind::Interrupted => Ok(Some(vec![])),
+                    _ => Err(err),
+                };
+            }
+            let events = match self.inotify.read_events(&mut self.buf) {
+                Ok(events) => events,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(Some(vec![])),
+                Err(err) => return Err(err),
+            };
+            let mut changed = vec![];
+            for event in events {
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    return Ok(None);
+                }
+                if event.mask.contains(EventMask::IGNORED) {
+                    self.files.remove(&event.wd);
+                    continue;
+                }
+                if let Some(file_nums) = self.files.get(&event.wd) {
+                    changed.extend(file_nums);
+                }
+                if let (Some(entries), Some(name)) = (self.dirs.get(&event.wd), event.name) {
+                    changed.extend(
+                        entries
+                            .iter()
+                            .filter(|(file_name, _)| file_name == name)
+                            .map(|(_, file_num)| file_num),
+                    );
+                }
+            }
+            changed.sort_unstable();
+            changed.dedup();
+            Ok(Some(changed))
+        }
+    }
+
+    fn is_remote(dir: &Path) -> io::Result<bool> {
+        let path = CString::new(dir.as_os_str().as_bytes())?;
+        let mut buf = MaybeUninit::<libc::statfs>::uninit();
+        if unsafe { libc::statfs(path.as_ptr(), buf.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let f_type = unsafe { buf.assume_init() }.f_type as u32;
+        Ok(REMOTE_FS_MAGICS.contains(&f_type))
+    }
+}
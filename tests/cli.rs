@@ -2,8 +2,12 @@ use anyhow::Result;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::fs::{self, File};
-use std::io::Read;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::Duration;
 
 const PRG: &str = "tailr";
 const EMPTY: &str = "tests/inputs/empty.txt";
@@ -11,6 +15,10 @@ const ONE: &str = "tests/inputs/one.txt";
 const TWO: &str = "tests/inputs/two.txt";
 const THREE: &str = "tests/inputs/three.txt";
 const TEN: &str = "tests/inputs/ten.txt";
+const ZERO: &str = "tests/inputs/zero.bin";
+const CRLF: &str = "tests/inputs/crlf.bin";
+const DOCS: &str = "tests/inputs/docs.yaml";
+const LOG: &str = "tests/inputs/app.log";
 
 fn random_string() -> String {
     rand::thread_rng()
@@ -29,16 +37,6 @@ fn gen_bad_file() -> String {
     }
 }
 
-#[test]
-fn dies_no_args() -> Result<()> {
-    Command::cargo_bin(PRG)?
-        .assert()
-        .failure()
-        .stderr(predicate::str::contains("Usage"));
-
-    Ok(())
-}
-
 #[test]
 fn dies_bad_bytes() -> Result<()> {
     let bad = random_string();
@@ -79,6 +77,19 @@ fn dies_bytes_and_lines() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn dies_bad_sleep_interval() -> Result<()> {
+    let bad = random_string();
+    let expected = format!("invalid number of seconds: '{bad}'");
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-s", &bad, EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+
+    Ok(())
+}
+
 #[test]
 fn skips_bad_file() -> Result<()> {
     let bad = gen_bad_file();
@@ -86,13 +97,35 @@ fn skips_bad_file() -> Result<()> {
     Command::cargo_bin(PRG)?
         .args([ONE, &bad, TWO])
         .assert()
+        .code(1)
         .stderr(predicate::str::is_match(expected)?);
 
     Ok(())
 }
 
+#[test]
+fn skips_directory() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([ONE, "tests/inputs", TWO])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("==> tests/inputs <==\n\n==> "))
+        .stderr("error reading 'tests/inputs': Is a directory\n");
+
+    Ok(())
+}
+
 fn run(args: &[&str], expected_file: &str) -> Result<()> {
-    // Extra work here due to lossy UTF
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .stdout(predicate::eq(expected));
+
+    Ok(())
+}
+
+fn run_lossy(args: &[&str], expected_file: &str) -> Result<()> {
     let mut file = File::open(expected_file)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
@@ -106,6 +139,96 @@ fn run(args: &[&str], expected_file: &str) -> Result<()> {
     Ok(())
 }
 
+fn run_stdin(args: &[&str], input_file: &str, expected_file: &str) -> Result<()> {
+    let input = fs::read_to_string(input_file)?;
+    let expected = fs::read_to_string(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn stdin_no_args() -> Result<()> {
+    run_stdin(&[], TEN, "tests/expected/stdin.ten.txt.out")
+}
+
+#[test]
+fn stdin_dash() -> Result<()> {
+    run_stdin(&["-"], TEN, "tests/expected/stdin.ten.txt.out")
+}
+
+#[test]
+fn stdin_n3() -> Result<()> {
+    run_stdin(&["-n", "3"], TEN, "tests/expected/ten.txt.n3.out")
+}
+
+#[test]
+fn stdin_n_plus_2() -> Result<()> {
+    run_stdin(&["-n", "+2", "-"], TEN, "tests/expected/ten.txt.n+2.out")
+}
+
+#[test]
+fn stdin_c8() -> Result<()> {
+    run_stdin(&["-c", "8"], TEN, "tests/expected/ten.txt.c8.out")
+}
+
+#[test]
+fn stdin_n200() -> Result<()> {
+    run_stdin(&["-n", "200"], TEN, "tests/expected/ten.txt.n200.out")
+}
+
+#[test]
+fn stdin_n0() -> Result<()> {
+    run_stdin(&["-n", "0"], TEN, "tests/expected/ten.txt.n0.out")
+}
+
+#[test]
+fn stdin_n_plus_0() -> Result<()> {
+    run_stdin(&["-n", "+0"], THREE, "tests/expected/three.txt.n+0.out")
+}
+
+#[test]
+fn stdin_n_minus_1_no_trailing_newline() -> Result<()> {
+    run_stdin(&["-n=-1"], TWO, "tests/expected/two.txt.n1.out")
+}
+
+#[test]
+fn stdin_c200() -> Result<()> {
+    run_stdin(&["-c", "200"], TEN, "tests/expected/ten.txt.c200.out")
+}
+
+#[test]
+fn stdin_c_plus_2() -> Result<()> {
+    run_stdin(&["-c", "+2"], THREE, "tests/expected/three.txt.c+2.out")
+}
+
+#[test]
+fn stdin_empty() -> Result<()> {
+    run_stdin(&["-n", "3"], EMPTY, "tests/expected/empty.txt.n3.out")
+}
+
+#[test]
+fn stdin_dev_stdin() -> Result<()> {
+    run_stdin(
+        &["-n", "3", "/dev/stdin"],
+        TEN,
+        "tests/expected/ten.txt.n3.out",
+    )
+}
+
+#[test]
+fn stdin_with_files() -> Result<()> {
+    run_stdin(
+        &["-n", "1", ONE, "-"],
+        TEN,
+        "tests/expected/stdin.all.n1.out",
+    )
+}
+
 #[test]
 fn empty() -> Result<()> {
     run(&[EMPTY], "tests/expected/empty.txt.out")
@@ -671,6 +794,524 @@ fn ten_c200() -> Result<()> {
     run(&[TEN, "-c", "200"], "tests/expected/ten.txt.c200.out")
 }
 
+#[test]
+fn ten_c1k() -> Result<()> {
+    run(&[TEN, "-c", "1K"], "tests/expected/ten.txt.c200.out")
+}
+
+#[test]
+fn ten_n1kb() -> Result<()> {
+    run(&[TEN, "-n", "1KB"], "tests/expected/ten.txt.n200.out")
+}
+
+#[test]
+fn ten_c10kb_lowercase() -> Result<()> {
+    run(&[TEN, "-c", "10kB"], "tests/expected/ten.txt.c200.out")
+}
+
+#[test]
+fn dies_count_too_large() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "+9E", TEN])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '+9E' for '--bytes <BYTES>': value too large",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn obsolete_lines() -> Result<()> {
+    run(&["-3", TEN], "tests/expected/ten.txt.n3.out")
+}
+
+#[test]
+fn obsolete_plus_lines() -> Result<()> {
+    run(&["+2l", TEN], "tests/expected/ten.txt.n+2.out")
+}
+
+#[test]
+fn obsolete_bytes() -> Result<()> {
+    run(&["-3c", TEN], "tests/expected/ten.txt.c3.out")
+}
+
+#[test]
+fn obsolete_default_count() -> Result<()> {
+    run(&["-l", TEN], "tests/expected/ten.txt.out")
+}
+
+#[test]
+fn obsolete_stdin() -> Result<()> {
+    run_stdin(&["-3"], TEN, "tests/expected/ten.txt.n3.out")
+}
+
+#[test]
+fn obsolete_multiple_files_not_expanded() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-3", ONE, TWO])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unexpected argument '-3'"));
+
+    Ok(())
+}
+
+#[test]
+fn obsolete_disabled_by_posix() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--posix", "+2", TEN])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("==> tests/inputs/ten.txt <=="))
+        .stderr(predicate::str::starts_with("+2: "));
+
+    Ok(())
+}
+
+#[test]
+fn zero_terminated() -> Result<()> {
+    run(&[ZERO, "-z", "-n", "2"], "tests/expected/zero.bin.z.n2.out")
+}
+
+#[test]
+fn zero_terminated_plus() -> Result<()> {
+    run(
+        &[ZERO, "--zero-terminated", "-n", "+3"],
+        "tests/expected/zero.bin.z.n+3.out",
+    )
+}
+
+#[test]
+fn zero_terminated_stdin() -> Result<()> {
+    run_stdin(
+        &["-z", "-n", "2"],
+        ZERO,
+        "tests/expected/stdin.zero.bin.z.n2.out",
+    )
+}
+
+#[test]
+fn delimiter_multi_byte() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([CRLF, "--delimiter", "\\r\\n", "-n", "2"])
+        .assert()
+        .success()
+        .stdout("two\nstill two\r\nthree\r\n");
+
+    Ok(())
+}
+
+#[test]
+fn delimiter_escape_stdin() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--delimiter", "\\x1e", "-n", "2"])
+        .write_stdin("a\x1eb\x1ec")
+        .assert()
+        .success()
+        .stdout("b\x1ec");
+
+    Ok(())
+}
+
+#[test]
+fn dies_bad_delimiter() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--delimiter", "\\x", CRLF])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid escape in delimiter"));
+
+    Ok(())
+}
+
+#[test]
+fn record_start() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([DOCS, "--record-start", "^---$", "-n", "2"])
+        .assert()
+        .success()
+        .stdout("---\nd: 4\n---\ne: 5\nf: 6\n");
+
+    Ok(())
+}
+
+#[test]
+fn record_start_plus() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([DOCS, "--record-start", "^---$", "-n", "+4"])
+        .assert()
+        .success()
+        .stdout("---\ne: 5\nf: 6\n");
+
+    Ok(())
+}
+
+#[test]
+fn entries() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([LOG, "--entries", "-n", "3"])
+        .assert()
+        .success()
+        .stdout(
+            "2024-01-02 03:04:06,200 ERROR Request failed\n\
+             java.lang.IllegalStateException: boom\n\
+             \tat com.example.Service.handle(Service.java:42)\n\
+             \tat com.example.Main.main(Main.java:7)\n\
+             2024-01-02 03:04:07,300 INFO  Retrying\n\
+             2024-01-02 03:04:08,400 WARN  Slow response\n\
+             \x20 took 1520 ms\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn entries_regex() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([LOG, "--entries=ERROR|WARN", "-n", "+3"])
+        .assert()
+        .success()
+        .stdout("2024-01-02 03:04:08,400 WARN  Slow response\n  took 1520 ms\n");
+
+    Ok(())
+}
+
+#[test]
+fn verbose() -> Result<()> {
+    run(&[ONE, "-v"], "tests/expected/one.txt.v.out")
+}
+
+#[test]
+fn verbose_stdin() -> Result<()> {
+    run_stdin(
+        &["--verbose", "-n", "1"],
+        TEN,
+        "tests/expected/stdin.ten.txt.v.n1.out",
+    )
+}
+
+#[test]
+fn quiet_overrides_verbose() -> Result<()> {
+    run(&[ONE, "-v", "-q"], "tests/expected/one.txt.out")
+}
+
+#[test]
+fn header_format() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([
+            "-n",
+            "1",
+            "--header-format",
+            "{{{index}}} {path} {size}",
+            ONE,
+            TWO,
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "{1} tests/inputs/one.txt 24\nÖne line, four wordś.\n\n\
+             {2} tests/inputs/two.txt 23\nFour words.\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn dies_bad_header_format() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--header-format", "{name}", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid header format: unknown placeholder '{name}'",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn prefix() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--prefix", "-n", "2", ONE, TWO])
+        .assert()
+        .success()
+        .stdout(
+            "tests/inputs/one.txt:Öne line, four wordś.\n\
+             tests/inputs/two.txt:Two lines.\n\
+             tests/inputs/two.txt:Four words.\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn prefix_align_separator() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--prefix", "--prefix-align", "--prefix-separator", " | "])
+        .args(["-n", "1", "-", TEN])
+        .write_stdin("no newline")
+        .assert()
+        .success()
+        .stdout(
+            "standard input       | no newline\n\
+             tests/inputs/ten.txt | ten\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn prefix_verbose() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--prefix", "-v", "-n", "1", ONE])
+        .assert()
+        .success()
+        .stdout("==> tests/inputs/one.txt <==\ntests/inputs/one.txt:Öne line, four wordś.\n");
+
+    Ok(())
+}
+
+#[test]
+fn line_numbers() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--line-numbers", "-n", "3", TEN])
+        .assert()
+        .success()
+        .stdout("8:eight\n9:nine\n10:ten\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["--line-numbers", "-n", "+9", TEN])
+        .assert()
+        .success()
+        .stdout("9:nine\n10:ten\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["--line-numbers", "-n", "+0", "-"])
+        .write_stdin("one\ntwo")
+        .assert()
+        .success()
+        .stdout("1:one\n2:two");
+
+    Ok(())
+}
+
+#[test]
+fn offsets() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--offsets", "-n", "2", TEN])
+        .assert()
+        .success()
+        .stdout("40:nine\n45:ten\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["--offsets", "--line-numbers", "-c", "5", "-"])
+        .write_stdin(fs::read(TEN)?)
+        .assert()
+        .success()
+        .stdout("9:44:\n10:45:ten\n");
+
+    Command::cargo_bin(PRG)?
+        .args([
+            "--offsets",
+            "--prefix",
+            "--prefix-separator",
+            " ",
+            "-n",
+            "1",
+            ONE,
+            TWO,
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "tests/inputs/one.txt 0 Öne line, four wordś.\n\
+             tests/inputs/two.txt 11 Four words.\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn grep() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "2", "--grep", "e", TEN])
+        .assert()
+        .success()
+        .stdout("nine\nten\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["-n", "+2", "--grep", "^T", "-i", "--line-numbers", TEN])
+        .assert()
+        .success()
+        .stdout("3:three\n10:ten\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["-n", "3", "--grep", "o", "--grep", "x", "-"])
+        .write_stdin(fs::read(TEN)?)
+        .assert()
+        .success()
+        .stdout("two\nfour\nsix\n");
+
+    Ok(())
+}
+
+#[test]
+fn grep_exclude() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "2", "--exclude", "e", "--offsets", TEN])
+        .assert()
+        .success()
+        .stdout("14:four\n24:six\n");
+
+    Command::cargo_bin(PRG)?
+        .args([
+            "-n",
+            "1",
+            "--entries",
+            "--grep",
+            "error",
+            "-i",
+            "--exclude",
+            "WARN",
+        ])
+        .arg(LOG)
+        .assert()
+        .success()
+        .stdout(
+            "2024-01-02 03:04:06,200 ERROR Request failed\n\
+             java.lang.IllegalStateException: boom\n\
+             \tat com.example.Service.handle(Service.java:42)\n\
+             \tat com.example.Main.main(Main.java:7)\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn grep_invalid() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--grep", "(", TEN])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unclosed group"));
+
+    Ok(())
+}
+
+#[test]
+fn color() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--color=always", "-n", "1", "--grep", "o", ONE, TWO])
+        .assert()
+        .success()
+        .stdout(
+            "\x1b[1m==> tests/inputs/one.txt <==\x1b[0m\n\
+             Öne line, f\x1b[1;31mo\x1b[0mur w\x1b[1;31mo\x1b[0mrdś.\n\
+             \n\x1b[1m==> tests/inputs/two.txt <==\x1b[0m\n\
+             F\x1b[1;31mo\x1b[0mur w\x1b[1;31mo\x1b[0mrds.\n",
+        );
+
+    Command::cargo_bin(PRG)?
+        .args(["--color", "--prefix", "-n", "1", ONE])
+        .assert()
+        .success()
+        .stdout("\x1b[35mtests/inputs/one.txt\x1b[0m\x1b[36m:\x1b[0mÖne line, four wordś.\n");
+
+    Ok(())
+}
+
+#[test]
+fn color_severity() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--color=always", "--severity-colors", "-n", "4", LOG])
+        .assert()
+        .success()
+        .stdout(
+            "\tat com.example.Main.main(Main.java:7)\n\
+             2024-01-02 03:04:07,300 INFO  Retrying\n\
+             \x1b[33m2024-01-02 03:04:08,400 WARN  Slow response\x1b[0m\n\
+             \x20 took 1520 ms\n",
+        );
+
+    Ok(())
+}
+
+#[test]
+fn color_never() -> Result<()> {
+    // 端末でない場合やNO_COLORが設定されている場合は色を付けない
+    run(
+        &["--color=never", "-n", "3", TEN],
+        "tests/expected/ten.txt.n3.out",
+    )?;
+    run(
+        &["--color=auto", "-n", "3", TEN],
+        "tests/expected/ten.txt.n3.out",
+    )?;
+
+    Command::cargo_bin(PRG)?
+        .env("NO_COLOR", "1")
+        .args(["-n", "1", "--grep", "ten", TEN])
+        .assert()
+        .success()
+        .stdout("ten\n");
+
+    Command::cargo_bin(PRG)?
+        .env("NO_COLOR", "1")
+        .args(["--prefix", "--line-numbers", "-n", "1", TEN])
+        .assert()
+        .success()
+        .stdout("tests/inputs/ten.txt:10:ten\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["--color=never", "--prefix", "-n", "1", TEN])
+        .assert()
+        .success()
+        .stdout("tests/inputs/ten.txt:ten\n");
+
+    Ok(())
+}
+
+#[test]
+fn output_format_json() -> Result<()> {
+    let ts = r#""ts":"\d{4}-\d{2}-\d{2}T[^"]+""#;
+    let expected = format!(
+        r#"^\{{"type":"header","file":"tests/inputs/one.txt","text":"==> tests/inputs/one.txt <=="\}}
+\{{"type":"line","file":"tests/inputs/one.txt","line_no":1,"offset":0,"text":"Öne line, four wordś.",{ts}\}}
+\{{"type":"header","file":"tests/inputs","text":"==> tests/inputs <=="\}}
+\{{"type":"error","file":"tests/inputs","message":"Is a directory"\}}
+\{{"type":"header","file":"tests/inputs/two.txt","text":"==> tests/inputs/two.txt <=="\}}
+\{{"type":"line","file":"tests/inputs/two.txt","line_no":2,"offset":11,"text":"Four words.",{ts}\}}
+$"#
+    );
+    Command::cargo_bin(PRG)?
+        .args(["--output-format=json", "-n", "1", ONE, "tests/inputs", TWO])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::is_match(expected)?);
+
+    // エラーのメッセージにはファイル名を含めない
+    Command::cargo_bin(PRG)?
+        .args(["--output-format=json", "missing.txt"])
+        .assert()
+        .code(1)
+        .stdout(
+            "{\"type\":\"error\",\"file\":\"missing.txt\",\
+             \"message\":\"No such file or directory (os error 2)\"}\n",
+        );
+
+    // UTF-8でない行はbase64で出力する
+    Command::cargo_bin(PRG)?
+        .args(["--output-format=json", "-n", "1", "-"])
+        .write_stdin(&b"\xff\xfe\n"[..])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""text_base64":"//4=""#));
+
+    Ok(())
+}
+
 #[test]
 fn ten_c_minus_200() -> Result<()> {
     run(&[TEN, "-c=-200"], "tests/expected/ten.txt.c200.out")
@@ -706,6 +1347,37 @@ fn ten_c_plus_2() -> Result<()> {
     run(&[TEN, "-c", "+2"], "tests/expected/ten.txt.c+2.out")
 }
 
+#[test]
+fn one_c3_lossy() -> Result<()> {
+    run_lossy(
+        &[ONE, "-c", "3", "--lossy"],
+        "tests/expected/one.txt.c3.out",
+    )
+}
+
+#[test]
+fn multiple_files_c3_lossy() -> Result<()> {
+    run_lossy(
+        &["--lossy", "-c", "3", TEN, EMPTY, ONE, THREE, TWO],
+        "tests/expected/all.c3.out",
+    )
+}
+
+#[test]
+fn lossy_char_across_blocks() -> Result<()> {
+    // ブロックの境界をまたぐ文字はそのまま出力する
+    let contents = format!("{}é", "a".repeat(8191));
+    let file = gen_tmp_file(&contents)?;
+    let output = Command::cargo_bin(PRG)?
+        .args(["--lossy", "-c", "+1", file.to_str().unwrap()])
+        .output()?;
+    fs::remove_file(&file)?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, contents);
+
+    Ok(())
+}
+
 #[test]
 fn multiple_files() -> Result<()> {
     run(&[TEN, EMPTY, ONE, THREE, TWO], "tests/expected/all.out")
@@ -814,3 +1486,493 @@ fn multiple_files_c_plus_3() -> Result<()> {
         "tests/expected/all.c+3.out",
     )
 }
+
+fn gen_tmp_file(contents: &str) -> Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!("tailr-{}", random_string()));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+fn append(path: &PathBuf, contents: &str) -> Result<()> {
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+fn spawn(args: &[&str]) -> Result<Child> {
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    thread::sleep(Duration::from_millis(500));
+    Ok(child)
+}
+
+fn kill(mut child: Child) -> Result<(String, String)> {
+    child.kill()?;
+    let output = child.wait_with_output()?;
+    Ok((
+        String::from_utf8(output.stdout)?,
+        String::from_utf8(output.stderr)?,
+    ))
+}
+
+#[test]
+fn follow_appended() -> Result<()> {
+    let file = gen_tmp_file("one\ntwo\n")?;
+    let child = spawn(&["-n", "1", "-f", file.to_str().unwrap()])?;
+    append(&file, "three\nfour")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "two\nthree\nfour");
+
+    Ok(())
+}
+
+#[test]
+fn follow_entries() -> Result<()> {
+    let file = gen_tmp_file("10:00:00 one\n10:00:01 two\n  more\n")?;
+    let child = spawn(&["--entries", "-n", "1", "-f", file.to_str().unwrap()])?;
+    append(&file, "10:00:02 three\n  at x\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "10:00:01 two\n  more\n10:00:02 three\n  at x\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_header_format() -> Result<()> {
+    let file1 = gen_tmp_file("one\n")?;
+    let file2 = gen_tmp_file("two\n")?;
+    let name1 = file1.to_str().unwrap();
+    let name2 = file2.to_str().unwrap();
+    let child = spawn(&["-f", "--header-format", "# {index} {path}", name1, name2])?;
+    append(&file1, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file1)?;
+    fs::remove_file(&file2)?;
+    assert_eq!(
+        stdout,
+        format!("# 1 {name1}\none\n\n# 2 {name2}\ntwo\n\n# 1 {name1}\nthree\n")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn follow_prefix() -> Result<()> {
+    let file1 = gen_tmp_file("one\n")?;
+    let file2 = gen_tmp_file("two\n")?;
+    let name1 = file1.to_str().unwrap();
+    let name2 = file2.to_str().unwrap();
+    let child = spawn(&["-f", "--prefix", name1, name2])?;
+    append(&file1, "thr")?;
+    thread::sleep(Duration::from_millis(1500));
+    append(&file2, "four\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    append(&file1, "ee\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file1)?;
+    fs::remove_file(&file2)?;
+    assert_eq!(
+        stdout,
+        format!("{name1}:one\n{name2}:two\n{name2}:four\n{name1}:three\n")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn follow_line_numbers() -> Result<()> {
+    let file = gen_tmp_file("one\ntwo\n")?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", "-n", "0", "--line-numbers", "--offsets", name])?;
+    append(&file, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    fs::write(&file, "four\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "3:8:three\n1:0:four\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_timestamp() -> Result<()> {
+    let file = gen_tmp_file("one\n")?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", "--timestamp=[%%]", name])?;
+    append(&file, "two\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "one\n[%] two\n");
+
+    let file = gen_tmp_file("")?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", "--timestamp", name])?;
+    append(&file, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    let (stamp, line) = stdout.split_once(' ').unwrap();
+    assert!(chrono::DateTime::parse_from_rfc3339(stamp).is_ok());
+    assert_eq!(line, "three\n");
+
+    Ok(())
+}
+
+#[test]
+fn timestamp_invalid() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "--timestamp=%Q", TEN])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid timestamp format '%Q'"));
+
+    Ok(())
+}
+
+#[test]
+fn timestamp_without_follow() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--timestamp", "-n", "1", ONE])
+        .assert()
+        .success()
+        .stdout("Öne line, four wordś.\n")
+        .stderr(predicate::str::contains(
+            "warning: --timestamp ignored; --timestamp is useful only when following",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn follow_grep() -> Result<()> {
+    let file = gen_tmp_file("ERROR one\nINFO two\n")?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", "-n", "1", "--grep", "ERROR", "--line-numbers", name])?;
+    append(&file, "INFO three\nERROR four\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "1:ERROR one\n4:ERROR four\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_output_format_json() -> Result<()> {
+    let file = gen_tmp_file("one\ntwo\n")?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", "-n", "0", "--output-format=json", name])?;
+    fs::write(&file, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        format!(r#"{{"type":"notice","file":"{name}","message":"file truncated"}}"#)
+    );
+    assert!(lines[1].starts_with(&format!(
+        r#"{{"type":"line","file":"{name}","line_no":1,"offset":0,"text":"three","ts":"#
+    )));
+
+    Ok(())
+}
+
+#[test]
+fn follow_entries_grep() -> Result<()> {
+    let file = gen_tmp_file(&fs::read_to_string(LOG)?)?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", "-n", "0", "--entries", "--grep", "ERROR", name])?;
+    append(
+        &file,
+        "2024-01-02 03:04:09,500 INFO  Done\n\
+         2024-01-02 03:04:10,600 ERROR again\n  at baz\n",
+    )?;
+    thread::sleep(Duration::from_millis(1500));
+    append(
+        &file,
+        "  at qux\n2024-01-02 03:04:11,700 INFO  Idle\n  waiting\n",
+    )?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(
+        stdout,
+        "2024-01-02 03:04:10,600 ERROR again\n  at baz\n  at qux\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn follow_entries_grep_continued() -> Result<()> {
+    // 最初に出力したエントリの続きも出力する
+    let contents = fs::read_to_string(LOG)? + "2024-01-02 03:04:09,500 ERROR Disk full\n";
+    let file = gen_tmp_file(&contents)?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", "-n", "1", "--entries", "--grep", "ERROR", name])?;
+    append(&file, "  at cont\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    append(&file, "2024-01-02 03:04:10,600 INFO  Done\n  at skipped\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(
+        stdout,
+        "2024-01-02 03:04:09,500 ERROR Disk full\n  at cont\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn follow_entries_grep_split() -> Result<()> {
+    // 後から書き込まれた行で一致したエントリも出力する
+    let file = gen_tmp_file(&fs::read_to_string(LOG)?)?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", "-n", "0", "--entries", "--grep", "boom", name])?;
+    append(&file, "2024-01-02 03:04:09,500 ERROR Failed\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    append(&file, "  boom\n2024-01-02 03:04:10,600 INFO  Done\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "2024-01-02 03:04:09,500 ERROR Failed\n  boom\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_n0() -> Result<()> {
+    let file = gen_tmp_file("one\ntwo\n")?;
+    let child = spawn(&["-n", "0", "-f", file.to_str().unwrap()])?;
+    append(&file, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "three\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_multiple_files() -> Result<()> {
+    let file1 = gen_tmp_file("one\n")?;
+    let file2 = gen_tmp_file("two\n")?;
+    let name1 = file1.to_str().unwrap();
+    let name2 = file2.to_str().unwrap();
+    let child = spawn(&["--follow", name1, name2])?;
+    append(&file1, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    append(&file2, "four\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file1)?;
+    fs::remove_file(&file2)?;
+    assert_eq!(
+        stdout,
+        format!(
+            "==> {name1} <==\none\n\n==> {name2} <==\ntwo\n\
+             \n==> {name1} <==\nthree\n\n==> {name2} <==\nfour\n"
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn follow_header_after_unfollowed_source() -> Result<()> {
+    // 追跡しないソースのヘッダーの後では、追記されたファイルのヘッダーを再び表示する
+    let file = gen_tmp_file("one\n")?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["-f", name, "tests/inputs"])?;
+    append(&file, "two\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(
+        stdout,
+        format!("==> {name} <==\none\n\n==> tests/inputs <==\n\n==> {name} <==\ntwo\n")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn follow_name_rename_rotation() -> Result<()> {
+    let file = gen_tmp_file("one\n")?;
+    let rotated = file.with_extension("1");
+    let child = spawn(&["-F", file.to_str().unwrap()])?;
+    fs::rename(&file, &rotated)?;
+    append(&rotated, "two\n")?;
+    fs::write(&file, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, stderr) = kill(child)?;
+    fs::remove_file(&file)?;
+    fs::remove_file(&rotated)?;
+    assert_eq!(stdout, "one\ntwo\nthree\n");
+    assert!(stderr.contains("has been replaced; following new file"));
+
+    Ok(())
+}
+
+#[test]
+fn follow_descriptor_rename_rotation() -> Result<()> {
+    let file = gen_tmp_file("one\n")?;
+    let rotated = file.with_extension("1");
+    let child = spawn(&["-f", file.to_str().unwrap()])?;
+    fs::rename(&file, &rotated)?;
+    append(&rotated, "two\n")?;
+    fs::write(&file, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, _) = kill(child)?;
+    fs::remove_file(&file)?;
+    fs::remove_file(&rotated)?;
+    assert_eq!(stdout, "one\ntwo\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_truncated() -> Result<()> {
+    let file = gen_tmp_file("one\ntwo\n")?;
+    let child = spawn(&["--follow=name", file.to_str().unwrap()])?;
+    fs::write(&file, "")?;
+    thread::sleep(Duration::from_millis(1500));
+    append(&file, "three\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, stderr) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "one\ntwo\nthree\n");
+    assert!(stderr.contains("file truncated"));
+
+    Ok(())
+}
+
+#[test]
+fn follow_retry_missing() -> Result<()> {
+    let file = std::env::temp_dir().join(format!("tailr-{}", random_string()));
+    let child = spawn(&["--follow=name", "--retry", file.to_str().unwrap()])?;
+    fs::write(&file, "one\n")?;
+    thread::sleep(Duration::from_millis(1500));
+    let (stdout, stderr) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "one\n");
+    assert!(stderr.contains("(os error 2)"));
+    assert!(stderr.contains("has appeared; following new file"));
+
+    Ok(())
+}
+
+#[test]
+fn follow_name_no_files_remaining() -> Result<()> {
+    let file = gen_tmp_file("one\n")?;
+    let child = spawn(&["--follow=name", file.to_str().unwrap()])?;
+    fs::remove_file(&file)?;
+    thread::sleep(Duration::from_millis(1500));
+    let output = child.wait_with_output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("no files remaining"));
+
+    Ok(())
+}
+
+#[test]
+fn follow_polling() -> Result<()> {
+    let file = gen_tmp_file("one\n")?;
+    let name = file.to_str().unwrap();
+    let child = spawn(&["--disable-inotify", "-s", "0.1", "-F", name])?;
+    append(&file, "two\n")?;
+    thread::sleep(Duration::from_millis(300));
+    fs::write(&file, "three\n")?;
+    thread::sleep(Duration::from_millis(300));
+    let (stdout, stderr) = kill(child)?;
+    fs::remove_file(&file)?;
+    assert_eq!(stdout, "one\ntwo\nthree\n");
+    assert!(stderr.contains("file truncated"));
+
+    Ok(())
+}
+
+#[test]
+fn follow_polling_rename_rotation() -> Result<()> {
+    let file = gen_tmp_file("one\n")?;
+    let rotated = file.with_extension("1");
+    let name = file.to_str().unwrap();
+    let child = spawn(&["--disable-inotify", "--sleep-interval=0.1", "-F", name])?;
+    fs::rename(&file, &rotated)?;
+    fs::write(&file, "two\n")?;
+    thread::sleep(Duration::from_millis(300));
+    let (stdout, stderr) = kill(child)?;
+    fs::remove_file(&file)?;
+    fs::remove_file(&rotated)?;
+    assert_eq!(stdout, "one\ntwo\n");
+    assert!(stderr.contains("has been replaced; following new file"));
+
+    Ok(())
+}
+
+fn wait_exit(mut child: Child) -> Result<std::process::Output> {
+    for _ in 0..50 {
+        if child.try_wait()?.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    child.kill().ok();
+    Ok(child.wait_with_output()?)
+}
+
+#[test]
+fn follow_until_pid_exits() -> Result<()> {
+    let file = gen_tmp_file("one\n")?;
+    let mut sleeper = std::process::Command::new("sleep").arg("1").spawn()?;
+    let pid = sleeper.id().to_string();
+    let child = spawn(&["-f", "--pid", &pid, "-s", "0.1", file.to_str().unwrap()])?;
+    append(&file, "two\n")?;
+    sleeper.wait()?;
+    let output = wait_exit(child)?;
+    fs::remove_file(&file)?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "one\ntwo\n");
+
+    Ok(())
+}
+
+#[test]
+fn follow_dead_pid() -> Result<()> {
+    let mut sleeper = std::process::Command::new("true").spawn()?;
+    sleeper.wait()?;
+    let pid = sleeper.id().to_string();
+    let child = spawn(&["-f", "--pid", &pid, ONE])?;
+    let output = wait_exit(child)?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, fs::read("tests/expected/one.txt.out")?);
+
+    Ok(())
+}
+
+#[test]
+fn pid_without_follow() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--pid", "1", ONE])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: PID ignored; --pid=PID is useful only when following",
+        ));
+
+    Ok(())
+}